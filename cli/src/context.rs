@@ -0,0 +1,147 @@
+//! RPC connection, signer and account fetching shared by every command
+
+use std::path::PathBuf;
+
+use anchor_lang::{AccountDeserialize, Discriminator, Space};
+use anyhow::{anyhow, Context as _, Result};
+use openfourr_client::{
+    pda, state, AgentProfile, ClientProfile, Dispute, Instruction, MilestonePlan, Platform, Pubkey, SplMint, Submission, Task,
+};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+
+pub struct Context {
+    rpc: RpcClient,
+    keypair_path: PathBuf,
+    keypair: Option<Keypair>,
+}
+
+impl Context {
+    pub fn new(url: &str, keypair_path: Option<PathBuf>) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(resolve_url(url), CommitmentConfig::confirmed()),
+            keypair_path: keypair_path.unwrap_or_else(default_keypair_path),
+            keypair: None,
+        }
+    }
+
+    /// Load the keypair on first use so read commands work without one
+    pub fn signer(&mut self) -> Result<&Keypair> {
+        if self.keypair.is_none() {
+            let keypair = read_keypair_file(&self.keypair_path)
+                .map_err(|err| anyhow!("reading keypair {}: {err}", self.keypair_path.display()))?;
+            self.keypair = Some(keypair);
+        }
+        Ok(self.keypair.as_ref().unwrap())
+    }
+
+    pub fn signer_key(&mut self) -> Result<Pubkey> {
+        Ok(self.signer()?.pubkey())
+    }
+
+    /// Sign with the loaded keypair, which also pays the fee, and wait for confirmation
+    pub fn send(&mut self, instruction: Instruction) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash().context("fetching blockhash")?;
+        let signer = self.signer()?;
+        let transaction =
+            Transaction::new_signed_with_payer(&[instruction], Some(&signer.pubkey()), &[signer], blockhash);
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+
+    fn data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let mut accounts = self.rpc.get_multiple_accounts(&[*address])?;
+        Ok(accounts.pop().flatten().map(|account| account.data))
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey, what: &str) -> Result<T> {
+        let data = self.data(address)?.ok_or_else(|| anyhow!("{what} {address} not found"))?;
+        state::decode(&data).map_err(|err| anyhow!("decoding {what} {address}: {err}"))
+    }
+
+    pub fn platform(&self) -> Result<Platform> {
+        self.fetch(&pda::platform(), "platform")
+    }
+
+    pub fn task(&self, address: &Pubkey) -> Result<Task> {
+        self.fetch(address, "task")
+    }
+
+    pub fn agent_profile(&self, owner: &Pubkey) -> Result<AgentProfile> {
+        self.fetch(&pda::agent(owner), "agent profile")
+    }
+
+    pub fn submission(&self, address: &Pubkey) -> Result<Submission> {
+        self.fetch(address, "submission")
+    }
+
+    /// Whether an account exists at `address`, e.g. the signer's submission before `submit_work`
+    pub fn exists(&self, address: &Pubkey) -> Result<bool> {
+        Ok(self.data(address)?.is_some())
+    }
+
+    pub fn dispute(&self, task: &Pubkey) -> Result<Dispute> {
+        self.fetch(&pda::dispute(task), "dispute")
+    }
+
+    pub fn milestone_plan(&self, task: &Pubkey) -> Result<MilestonePlan> {
+        self.fetch(&pda::milestone_plan(task), "milestone plan")
+    }
+
+    /// `None` until the client posts their first task
+    pub fn client_profile(&self, client: &Pubkey) -> Result<Option<ClientProfile>> {
+        let Some(data) = self.data(&pda::client_profile(client))? else {
+            return Ok(None);
+        };
+        let profile = state::decode(&data).map_err(|err| anyhow!("decoding client profile of {client}: {err}"))?;
+        Ok(Some(profile))
+    }
+
+    /// Every task account owned by the program, both global and per-client
+    pub fn tasks(&self) -> Result<Vec<(Pubkey, Task)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize((8 + Task::INIT_SPACE) as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, Task::DISCRIMINATOR.to_vec())),
+            ]),
+            account_config: RpcAccountInfoConfig { encoding: Some(UiAccountEncoding::Base64), ..Default::default() },
+            ..Default::default()
+        };
+        let accounts = self.rpc.get_program_accounts_with_config(&openfourr_client::ID, config)?;
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| state::decode_task(&account.data).ok().map(|task| (address, task)))
+            .collect())
+    }
+
+    /// Mint and owning token program of `mint`, or `None` for SOL
+    pub fn spl(&self, mint: Option<Pubkey>) -> Result<Option<SplMint>> {
+        let Some(mint) = mint else {
+            return Ok(None);
+        };
+        let account = self.rpc.get_account(&mint).with_context(|| format!("fetching mint {mint}"))?;
+        Ok(Some(SplMint::new(mint, account.owner)))
+    }
+}
+
+/// Expand the Solana CLI cluster monikers; anything else is used as the URL
+fn resolve_url(url: &str) -> String {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+    .to_string()
+}
+
+fn default_keypair_path() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/solana/id.json")
+}
@@ -0,0 +1,738 @@
+//! `openfourr`: send Openfourr instructions and inspect program state from the command line.
+//!
+//! Works against any RPC endpoint (`--url`, or the `localhost`/`devnet`/`testnet`/`mainnet-beta`
+//! monikers), signing with a Solana CLI keypair file. Amounts are in lamports, or in base units
+//! of the mint for SPL tasks.
+
+mod context;
+mod output;
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use openfourr_client::{
+    instructions, pda, DisputeResolution, Instruction, MilestoneInput, Pubkey, SlashTarget, SplMint, Task, TaskMode,
+    TaskOptions,
+};
+
+use context::Context;
+use output::Format;
+
+#[derive(Parser)]
+#[command(name = "openfourr", version, about = "Operate the Openfourr marketplace program")]
+struct Cli {
+    /// RPC URL or cluster moniker (localhost, devnet, testnet, mainnet-beta)
+    #[arg(short, long, global = true, env = "OPENFOURR_RPC_URL", default_value = "localhost")]
+    url: String,
+    /// Keypair file that signs and pays for transactions [default: ~/.config/solana/id.json]
+    #[arg(short, long, global = true, env = "OPENFOURR_KEYPAIR")]
+    keypair: Option<PathBuf>,
+    /// Format of account dumps and transaction results
+    #[arg(short, long, global = true, value_enum, default_value_t = Format::Table)]
+    output: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the platform account with the signer as authority
+    Initialize {
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Register the signer as an agent
+    RegisterAgent {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        bio: String,
+        /// Repeat for each skill
+        #[arg(long = "skill")]
+        skills: Vec<String>,
+        #[arg(long, default_value_t = 0)]
+        hourly_rate: u64,
+    },
+    /// Post a task and fund its escrow
+    CreateTask(CreateTaskArgs),
+    /// Deposit, unstake or withdraw the signer's agent stake
+    #[command(subcommand)]
+    Stake(Stake),
+    /// Claim an exclusive-claim task, or a bidding task whose bid the client accepted
+    Claim { task: TaskRef },
+    /// Bid on a bidding task
+    Bid {
+        task: TaskRef,
+        #[arg(long)]
+        price: u64,
+        #[arg(long)]
+        eta_hours: u64,
+    },
+    /// Accept an agent's bid, refunding the rest of the budget to the signer
+    AcceptBid {
+        task: TaskRef,
+        /// Owner wallet of the bidding agent
+        #[arg(long)]
+        agent: Pubkey,
+    },
+    /// Withdraw the signer's pending bid
+    WithdrawBid { task: TaskRef },
+    /// Submit work: applies to open competitions, or delivers on a claimed or accepted-bid task
+    Submit {
+        task: TaskRef,
+        #[arg(long = "submission-url")]
+        url: String,
+        #[arg(long, default_value = "")]
+        notes: String,
+    },
+    /// Pay agents' submissions: picks open-competition winners or approves assigned work
+    SelectWinner {
+        task: TaskRef,
+        /// Owner wallet of a winning agent; repeat in place order on tasks with a payout schedule
+        #[arg(long = "agent", required = true)]
+        agents: Vec<Pubkey>,
+        /// 1 to 5, one per agent
+        #[arg(long = "rating", required = true)]
+        ratings: Vec<u8>,
+    },
+    /// Split a task into milestones or release the next one
+    #[command(subcommand)]
+    Milestones(Milestones),
+    /// Issue or finalize a judge's verdict
+    #[command(subcommand)]
+    Judge(Judge),
+    /// Cancel an open task and refund its escrow to the signer
+    Cancel { task: TaskRef },
+    /// Request, approve or deny a deadline extension
+    #[command(subcommand)]
+    Extend(Extend),
+    /// Dispute a task as its client or a submitting agent, locking the platform's dispute bond
+    Dispute {
+        task: TaskRef,
+        #[arg(long)]
+        reason: String,
+        #[arg(long, default_value = "")]
+        evidence_uri: String,
+    },
+    /// Settle a disputed task as the platform authority or arbiter
+    ResolveDispute {
+        task: TaskRef,
+        #[arg(long, value_enum)]
+        resolution: Resolution,
+        /// Agent's share of the remaining bounty for `--resolution split`
+        #[arg(long, required_if_eq("resolution", "split"))]
+        agent_bps: Option<u16>,
+        /// Owner wallet of the disputed agent [default: the task's assigned agent]
+        #[arg(long)]
+        agent: Option<Pubkey>,
+        /// 1 to 5, recorded when the agent is paid
+        #[arg(long)]
+        rating: Option<u8>,
+        /// Slash the agent's stake to the client or the treasury
+        #[arg(long, value_enum)]
+        slash: Option<Slash>,
+        /// Return the dispute bond to the raiser instead of forfeiting it
+        #[arg(long)]
+        return_bond: bool,
+    },
+    /// Refund a task 7 days past its deadline to its client, or a funder's contribution on a finished task
+    Refund {
+        task: TaskRef,
+        /// Claim a contribution's refund on a crowdfunded task instead
+        #[arg(long)]
+        contribution: bool,
+        /// Funder whose contribution to refund; defaults to the signer
+        #[arg(long, requires = "contribution")]
+        funder: Option<Pubkey>,
+    },
+    /// Show the platform account
+    Platform,
+    /// Show a task
+    Task { task: TaskRef },
+    /// List every task
+    Tasks {
+        /// Only tasks posted by this wallet
+        #[arg(long)]
+        client: Option<Pubkey>,
+    },
+    /// Show an agent profile [default: the signer's]
+    Agent { owner: Option<Pubkey> },
+}
+
+#[derive(Args)]
+struct CreateTaskArgs {
+    #[arg(long)]
+    title: String,
+    #[arg(long)]
+    description: String,
+    #[arg(long, default_value = "")]
+    requirements: String,
+    #[arg(long)]
+    category: String,
+    /// Bounty escrowed from the signer
+    #[arg(long)]
+    bounty: u64,
+    #[arg(long)]
+    deadline_hours: u64,
+    #[arg(long, value_enum, default_value_t = Mode::Open)]
+    mode: Mode,
+    /// SPL mint of the bounty [default: SOL]
+    #[arg(long)]
+    mint: Option<Pubkey>,
+    /// Let the platform judge approve submissions
+    #[arg(long)]
+    use_judge: bool,
+    #[arg(long)]
+    commit_reveal: bool,
+    /// Repeat to restrict the task to these agent owners
+    #[arg(long = "invite")]
+    invited_agents: Vec<Pubkey>,
+    #[arg(long)]
+    direct_hire: Option<Pubkey>,
+    #[arg(long)]
+    crowdfunded: bool,
+    #[arg(long, default_value_t = 0)]
+    min_stake: u64,
+    /// Basis points per place, best first, e.g. 6000,3000,1000
+    #[arg(long, value_delimiter = ',')]
+    payout_schedule: Vec<u16>,
+    /// Key the task by the signer's own nonce instead of the global task counter
+    #[arg(long)]
+    per_client: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Open,
+    Exclusive,
+    Bidding,
+}
+
+impl From<Mode> for TaskMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Open => TaskMode::OpenCompetition,
+            Mode::Exclusive => TaskMode::ExclusiveClaim,
+            Mode::Bidding => TaskMode::Bidding,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Extend {
+    /// Ask for more time as the task's agent
+    Request {
+        task: TaskRef,
+        #[arg(long)]
+        hours: u64,
+    },
+    /// Grant the pending request as the client
+    Approve { task: TaskRef },
+    /// Refuse the pending request as the client
+    Deny { task: TaskRef },
+}
+
+#[derive(Subcommand)]
+enum Stake {
+    /// Deposit lamports into the stake vault
+    Deposit {
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Start the cooldown on part of the stake
+    Unstake {
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Withdraw stake whose cooldown has passed
+    Withdraw,
+}
+
+#[derive(Subcommand)]
+enum Milestones {
+    /// Split an exclusive-claim task's bounty before anyone claims it
+    Add {
+        task: TaskRef,
+        /// `TITLE:AMOUNT:DEADLINE_HOURS`; repeat in order, amounts summing to the bounty
+        #[arg(long = "milestone", required = true)]
+        milestones: Vec<MilestoneArg>,
+    },
+    /// Release the next milestone to the assigned agent, as the client or the task judge
+    Release {
+        task: TaskRef,
+        /// 1 to 5, recorded when the last milestone completes the task
+        #[arg(long)]
+        rating: Option<u8>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Judge {
+    /// Approve an agent's submission, starting the client's challenge window
+    Approve {
+        task: TaskRef,
+        #[arg(long)]
+        agent: Pubkey,
+        /// 1 to 5
+        #[arg(long)]
+        rating: u8,
+    },
+    /// Reject an agent's submission, slashing their stake to the client
+    Reject {
+        task: TaskRef,
+        #[arg(long)]
+        agent: Pubkey,
+        #[arg(long)]
+        reason: String,
+    },
+    /// Release escrow per the verdict once the challenge window has passed
+    Finalize { task: TaskRef },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Resolution {
+    Award,
+    Refund,
+    Split,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Slash {
+    Client,
+    Treasury,
+}
+
+impl From<Slash> for SlashTarget {
+    fn from(slash: Slash) -> Self {
+        match slash {
+            Slash::Client => SlashTarget::Client,
+            Slash::Treasury => SlashTarget::Treasury,
+        }
+    }
+}
+
+/// `TITLE:AMOUNT:DEADLINE_HOURS`; the title may itself contain colons
+#[derive(Clone)]
+struct MilestoneArg(MilestoneInput);
+
+impl FromStr for MilestoneArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{value}` is not TITLE:AMOUNT:DEADLINE_HOURS");
+        let mut parts = value.rsplitn(3, ':');
+        let deadline_hours = parts.next().and_then(|hours| hours.parse().ok()).ok_or_else(invalid)?;
+        let amount = parts.next().and_then(|amount| amount.parse().ok()).ok_or_else(invalid)?;
+        let title = parts.next().ok_or_else(invalid)?.to_string();
+        Ok(Self(MilestoneInput { title, amount, deadline_hours }))
+    }
+}
+
+/// A task account address, or the id of a task opened through `create_task`
+#[derive(Clone, Copy)]
+struct TaskRef(Pubkey);
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(task_id) = value.parse::<u64>() {
+            return Ok(Self(pda::task(task_id)));
+        }
+        Pubkey::from_str(value).map(Self).map_err(|_| format!("`{value}` is neither a task id nor an address"))
+    }
+}
+
+/// Payout instruction for `select-winner`: `select_winners` on tasks with a payout schedule,
+/// otherwise `select_winner` on open competitions and `approve_work` on assigned work
+fn select_winner(
+    client: &Pubkey,
+    task: &Pubkey,
+    state: &Task,
+    agents: &[Pubkey],
+    ratings: Vec<u8>,
+    spl: Option<SplMint>,
+) -> Result<Instruction> {
+    let escrow = pda::task_escrow(task, state);
+    if agents.len() != ratings.len() {
+        bail!("pass one --rating per --agent");
+    }
+    if state.milestone_count > 0 {
+        bail!("task {task} pays by milestone; run `openfourr milestones release` instead");
+    }
+    if !state.payout_schedule.is_empty() {
+        if agents.len() > state.payout_schedule.len() {
+            bail!("task {task} pays at most {} winners", state.payout_schedule.len());
+        }
+        return Ok(instructions::select_winners(client, task, &escrow, agents, ratings, spl));
+    }
+    let ([agent], [rating]) = (agents, ratings.as_slice()) else {
+        bail!("task {task} pays a single winner");
+    };
+    Ok(match state.mode {
+        TaskMode::OpenCompetition => instructions::select_winner(client, task, &escrow, agent, *rating, spl),
+        TaskMode::ExclusiveClaim | TaskMode::Bidding => instructions::approve_work(client, task, &escrow, agent, *rating, spl),
+    })
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut ctx = Context::new(&cli.url, cli.keypair);
+    run(&mut ctx, cli.output, cli.command)
+}
+
+fn run(ctx: &mut Context, format: Format, command: Command) -> Result<()> {
+    match command {
+        Command::Initialize { fee_bps } => {
+            let authority = ctx.signer_key()?;
+            let signature = ctx.send(instructions::initialize(&authority, fee_bps))?;
+            output::print_sent(format, vec![("signature", signature.to_string()), ("platform", pda::platform().to_string())]);
+        }
+        Command::RegisterAgent { name, bio, skills, hourly_rate } => {
+            let owner = ctx.signer_key()?;
+            let signature = ctx.send(instructions::register_agent(&owner, name, bio, skills, hourly_rate))?;
+            output::print_sent(format, vec![("signature", signature.to_string()), ("agent", pda::agent(&owner).to_string())]);
+        }
+        Command::CreateTask(args) => create_task(ctx, format, args)?,
+        Command::Submit { task: TaskRef(task), url, notes } => {
+            let agent = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let instruction = match state.mode {
+                TaskMode::OpenCompetition if state.commit_reveal => {
+                    bail!("task {task} takes sealed submissions; commit and reveal them through the client crate")
+                }
+                TaskMode::OpenCompetition => instructions::submit_application(&agent, &task, url, notes),
+                TaskMode::ExclusiveClaim | TaskMode::Bidding => {
+                    if state.assigned_agent != Some(agent) || !ctx.exists(&pda::submission(&task, &agent))? {
+                        bail!("task {task} is not claimed by the signer; run `openfourr claim` first");
+                    }
+                    instructions::submit_work(&agent, &task, url, notes)
+                }
+            };
+            let signature = ctx.send(instruction)?;
+            output::print_sent(
+                format,
+                vec![("signature", signature.to_string()), ("submission", pda::submission(&task, &agent).to_string())],
+            );
+        }
+        Command::SelectWinner { task: TaskRef(task), agents, ratings } => {
+            let client = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let spl = ctx.spl(state.mint)?;
+            let signature = ctx.send(select_winner(&client, &task, &state, &agents, ratings, spl)?)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Stake(stake) => {
+            let owner = ctx.signer_key()?;
+            let instruction = match stake {
+                Stake::Deposit { amount } => instructions::stake(&owner, amount),
+                Stake::Unstake { amount } => instructions::request_unstake(&owner, amount),
+                Stake::Withdraw => instructions::withdraw_stake(&owner),
+            };
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Claim { task: TaskRef(task) } => {
+            let agent = ctx.signer_key()?;
+            let signature = ctx.send(instructions::claim_task(&agent, &task))?;
+            output::print_sent(
+                format,
+                vec![("signature", signature.to_string()), ("submission", pda::submission(&task, &agent).to_string())],
+            );
+        }
+        Command::Bid { task: TaskRef(task), price, eta_hours } => {
+            let agent = ctx.signer_key()?;
+            let signature = ctx.send(instructions::submit_bid(&agent, &task, price, eta_hours))?;
+            output::print_sent(format, vec![("signature", signature.to_string()), ("bid", pda::bid(&task, &agent).to_string())]);
+        }
+        Command::AcceptBid { task: TaskRef(task), agent } => {
+            let client = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let spl = ctx.spl(state.mint)?;
+            let escrow = pda::task_escrow(&task, &state);
+            let signature = ctx.send(instructions::accept_bid(&client, &task, &escrow, &agent, spl))?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::WithdrawBid { task: TaskRef(task) } => {
+            let agent = ctx.signer_key()?;
+            let signature = ctx.send(instructions::withdraw_bid(&agent, &task))?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Milestones(Milestones::Add { task: TaskRef(task), milestones }) => {
+            let client = ctx.signer_key()?;
+            let milestones = milestones.into_iter().map(|MilestoneArg(milestone)| milestone).collect();
+            let signature = ctx.send(instructions::add_milestones(&client, &task, milestones))?;
+            output::print_sent(
+                format,
+                vec![("signature", signature.to_string()), ("milestone_plan", pda::milestone_plan(&task).to_string())],
+            );
+        }
+        Command::Milestones(Milestones::Release { task: TaskRef(task), rating }) => {
+            let releaser = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let Some(agent) = state.assigned_agent else {
+                bail!("task {task} has no assigned agent to pay");
+            };
+            let plan = ctx.milestone_plan(&task)?;
+            let spl = ctx.spl(state.mint)?;
+            let escrow = pda::task_escrow(&task, &state);
+            let instruction = instructions::release_milestone(
+                &releaser,
+                &task,
+                &escrow,
+                &state.client,
+                &agent,
+                plan.released_count,
+                rating,
+                spl,
+            );
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Judge(judge) => {
+            let signer = ctx.signer_key()?;
+            let instruction = match judge {
+                Judge::Approve { task: TaskRef(task), agent, rating } => {
+                    let state = ctx.task(&task)?;
+                    instructions::judge_verdict(&signer, &task, &state.client, &agent, true, rating, String::new())
+                }
+                Judge::Reject { task: TaskRef(task), agent, reason } => {
+                    let state = ctx.task(&task)?;
+                    instructions::judge_verdict(&signer, &task, &state.client, &agent, false, 0, reason)
+                }
+                Judge::Finalize { task: TaskRef(task) } => {
+                    let state = ctx.task(&task)?;
+                    let Some(verdict) = state.verdict else {
+                        bail!("task {task} has no verdict to finalize");
+                    };
+                    let agent = ctx.submission(&verdict.submission)?.agent;
+                    let spl = ctx.spl(state.mint)?;
+                    let escrow = pda::task_escrow(&task, &state);
+                    instructions::finalize_verdict(&signer, &task, &escrow, &state.client, &agent, spl)
+                }
+            };
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Cancel { task: TaskRef(task) } => {
+            let client = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let spl = ctx.spl(state.mint)?;
+            let escrow = pda::task_escrow(&task, &state);
+            let signature = ctx.send(instructions::cancel_task(&client, &task, &escrow, state.milestone_count > 0, spl))?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Extend(extend) => {
+            let signer = ctx.signer_key()?;
+            let instruction = match extend {
+                Extend::Request { task: TaskRef(task), hours } => instructions::request_extension(&signer, &task, hours),
+                Extend::Approve { task: TaskRef(task) } => instructions::approve_extension(&signer, &task),
+                Extend::Deny { task: TaskRef(task) } => instructions::deny_extension(&signer, &task),
+            };
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Dispute { task: TaskRef(task), reason, evidence_uri } => {
+            let raiser = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let submission = (raiser != state.client).then(|| pda::submission(&task, &raiser));
+            let signature = ctx.send(instructions::raise_dispute(&raiser, &task, reason, evidence_uri, submission))?;
+            output::print_sent(format, vec![("signature", signature.to_string()), ("dispute", pda::dispute(&task).to_string())]);
+        }
+        Command::ResolveDispute { task: TaskRef(task), resolution, agent_bps, agent, rating, slash, return_bond } => {
+            let arbiter = ctx.signer_key()?;
+            let state = ctx.task(&task)?;
+            let dispute = ctx.dispute(&task)?;
+            let resolution = match resolution {
+                Resolution::Award => DisputeResolution::AwardSubmission,
+                Resolution::Refund => DisputeResolution::RefundClient,
+                Resolution::Split => DisputeResolution::Split { agent_bps: agent_bps.unwrap_or_default() },
+            };
+            let agent = agent.or(state.assigned_agent);
+            let pays_agent = !matches!(resolution, DisputeResolution::RefundClient);
+            if agent.is_none() && (pays_agent || slash.is_some()) {
+                bail!("task {task} has no assigned agent; pass --agent");
+            }
+            let spl = ctx.spl(state.mint)?;
+            let escrow = pda::task_escrow(&task, &state);
+            let instruction = instructions::resolve_dispute(
+                &arbiter,
+                &task,
+                &escrow,
+                &state.client,
+                &dispute.raised_by,
+                agent,
+                resolution,
+                rating,
+                slash.map(Into::into),
+                return_bond,
+                spl,
+            );
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Refund { task: TaskRef(task), contribution, funder } => {
+            let state = ctx.task(&task)?;
+            let spl = ctx.spl(state.mint)?;
+            let escrow = pda::task_escrow(&task, &state);
+            let instruction = if contribution {
+                let payer = ctx.signer_key()?;
+                instructions::claim_refund(&payer, &funder.unwrap_or(payer), &task, &escrow, &state.client, spl)
+            } else {
+                instructions::auto_refund_expired(&ctx.signer_key()?, &task, &escrow, &state.client, spl)
+            };
+            let signature = ctx.send(instruction)?;
+            output::print_sent(format, vec![("signature", signature.to_string())]);
+        }
+        Command::Platform => {
+            let platform = ctx.platform()?;
+            output::print_object(format, &output::platform_json(&pda::platform(), &platform));
+        }
+        Command::Task { task: TaskRef(task) } => {
+            let state = ctx.task(&task)?;
+            output::print_object(format, &output::task_json(&task, &state));
+        }
+        Command::Tasks { client } => {
+            let mut tasks = ctx.tasks()?;
+            tasks.retain(|(_, task)| client.is_none_or(|client| task.client == client));
+            tasks.sort_by_key(|(_, task)| (task.created_at, task.id));
+            let tasks: Vec<_> = tasks.iter().map(|(address, task)| output::task_json(address, task)).collect();
+            output::print_list(format, &tasks, output::TASK_COLUMNS);
+        }
+        Command::Agent { owner } => {
+            let owner = match owner {
+                Some(owner) => owner,
+                None => ctx.signer_key()?,
+            };
+            let agent = ctx.agent_profile(&owner)?;
+            output::print_object(format, &output::agent_json(&pda::agent(&owner), &agent));
+        }
+    }
+    Ok(())
+}
+
+fn create_task(ctx: &mut Context, format: Format, args: CreateTaskArgs) -> Result<()> {
+    let client = ctx.signer_key()?;
+    let spl = ctx.spl(args.mint)?;
+    let options = TaskOptions {
+        mode: args.mode.into(),
+        use_judge: args.use_judge,
+        commit_reveal: args.commit_reveal,
+        invited_agents: args.invited_agents,
+        direct_hire: args.direct_hire,
+        crowdfunded: args.crowdfunded,
+        min_stake: args.min_stake,
+        payout_schedule: args.payout_schedule,
+    };
+    let (task, instruction) = if args.per_client {
+        let nonce = ctx.client_profile(&client)?.map_or(0, |profile| profile.task_nonce);
+        let instruction = instructions::create_client_task(
+            &client,
+            nonce,
+            args.title,
+            args.description,
+            args.requirements,
+            args.category,
+            args.bounty,
+            args.deadline_hours,
+            options,
+            spl,
+        );
+        (pda::client_task(&client, nonce), instruction)
+    } else {
+        let task_id = ctx.platform()?.total_tasks;
+        let instruction = instructions::create_task(
+            &client,
+            task_id,
+            args.title,
+            args.description,
+            args.requirements,
+            args.category,
+            args.bounty,
+            args.deadline_hours,
+            options,
+            spl,
+        );
+        (pda::task(task_id), instruction)
+    };
+    let signature = ctx.send(instruction)?;
+    output::print_sent(format, vec![("signature", signature.to_string()), ("task", task.to_string())]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AnchorDeserialize, Space};
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn task_ref_accepts_ids_and_addresses() {
+        assert_eq!(TaskRef::from_str("3").unwrap().0, pda::task(3));
+        let address = Pubkey::new_unique();
+        assert_eq!(TaskRef::from_str(&address.to_string()).unwrap().0, address);
+        assert!(TaskRef::from_str("task-3").is_err());
+    }
+
+    /// A decoded all-zero task: open competition, no schedule, no milestones
+    fn task_state(mode: TaskMode, payout_schedule: Vec<u16>) -> Task {
+        let mut state = Task::deserialize(&mut &vec![0; Task::INIT_SPACE][..]).unwrap();
+        state.mode = mode;
+        state.payout_schedule = payout_schedule;
+        state
+    }
+
+    fn instruction_name(instruction: &Instruction) -> &'static str {
+        use openfourr_client::openfourr::instruction as ix;
+        use anchor_lang::Discriminator;
+        let data = instruction.data.as_slice();
+        if data.starts_with(ix::SelectWinners::DISCRIMINATOR) {
+            "select_winners"
+        } else if data.starts_with(ix::SelectWinner::DISCRIMINATOR) {
+            "select_winner"
+        } else if data.starts_with(ix::ApproveWork::DISCRIMINATOR) {
+            "approve_work"
+        } else {
+            "other"
+        }
+    }
+
+    #[test]
+    fn select_winner_picks_the_payout_instruction() {
+        let (client, task) = (Pubkey::new_unique(), pda::task(0));
+        let agents = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let select = |state: &Task, agents: &[Pubkey], ratings: Vec<u8>| select_winner(&client, &task, state, agents, ratings, None);
+
+        let open = task_state(TaskMode::OpenCompetition, vec![]);
+        assert_eq!(instruction_name(&select(&open, &agents[..1], vec![5]).unwrap()), "select_winner");
+        assert!(select(&open, &agents, vec![5, 4]).is_err());
+        assert!(select(&open, &agents[..1], vec![5, 4]).is_err());
+
+        let claimed = task_state(TaskMode::ExclusiveClaim, vec![]);
+        assert_eq!(instruction_name(&select(&claimed, &agents[..1], vec![5]).unwrap()), "approve_work");
+
+        let scheduled = task_state(TaskMode::OpenCompetition, vec![7000, 3000]);
+        assert_eq!(instruction_name(&select(&scheduled, &agents, vec![5, 4]).unwrap()), "select_winners");
+        let three = [agents[0], agents[1], Pubkey::new_unique()];
+        assert!(select(&scheduled, &three, vec![5, 4, 3]).is_err());
+
+        let mut milestones = task_state(TaskMode::ExclusiveClaim, vec![]);
+        milestones.milestone_count = 2;
+        assert!(select(&milestones, &agents[..1], vec![5]).is_err());
+    }
+
+    #[test]
+    fn milestone_arg_splits_from_the_right() {
+        let MilestoneArg(milestone) = MilestoneArg::from_str("Phase 1: design:400:48").unwrap();
+        assert_eq!(milestone.title, "Phase 1: design");
+        assert_eq!((milestone.amount, milestone.deadline_hours), (400, 48));
+        assert!(MilestoneArg::from_str("design:400").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! JSON and table rendering of decoded accounts and command results
+
+use clap::ValueEnum;
+use openfourr_client::{AgentProfile, Platform, Pubkey, Task, TaskMode, TaskStatus};
+use serde_json::{json, Map, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+/// Columns shown per task by `openfourr tasks` in table mode
+pub const TASK_COLUMNS: &[&str] = &["address", "id", "status", "mode", "bounty_amount", "submission_count", "deadline", "title"];
+
+fn key(key: &Pubkey) -> Value {
+    Value::String(key.to_string())
+}
+
+fn opt_key(key: Option<Pubkey>) -> Value {
+    key.map_or(Value::Null, |key| Value::String(key.to_string()))
+}
+
+fn status(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Open => "open",
+        TaskStatus::InProgress => "in-progress",
+        TaskStatus::PendingReview => "pending-review",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Rejected => "rejected",
+        TaskStatus::Cancelled => "cancelled",
+        TaskStatus::Disputed => "disputed",
+    }
+}
+
+fn mode(mode: TaskMode) -> &'static str {
+    match mode {
+        TaskMode::OpenCompetition => "open-competition",
+        TaskMode::ExclusiveClaim => "exclusive-claim",
+        TaskMode::Bidding => "bidding",
+    }
+}
+
+pub fn platform_json(address: &Pubkey, platform: &Platform) -> Value {
+    json!({
+        "address": key(address),
+        "authority": key(&platform.authority),
+        "pending_authority": opt_key(platform.pending_authority),
+        "fee_bps": platform.fee_bps,
+        "total_tasks": platform.total_tasks,
+        "total_completed": platform.total_completed,
+        "total_volume": platform.total_volume,
+        "total_fees_collected": platform.total_fees_collected,
+        "arbiter": opt_key(platform.arbiter),
+        "judge": opt_key(platform.judge),
+        "paused": platform.paused,
+        "retention_period": platform.retention_period,
+        "slash_bps": platform.slash_bps,
+        "dispute_bond": platform.dispute_bond,
+    })
+}
+
+pub fn agent_json(address: &Pubkey, agent: &AgentProfile) -> Value {
+    json!({
+        "address": key(address),
+        "owner": key(&agent.owner),
+        "name": agent.name,
+        "bio": agent.bio,
+        "skills": agent.skills,
+        "hourly_rate": agent.hourly_rate,
+        "is_active": agent.is_active,
+        "tasks_completed": agent.tasks_completed,
+        "tasks_failed": agent.tasks_failed,
+        "total_earned": agent.total_earned,
+        "rating_sum": agent.rating_sum,
+        "rating_count": agent.rating_count,
+        "open_submissions": agent.open_submissions,
+        "staked": agent.staked,
+        "unstaking": agent.unstaking,
+        "unstake_available_at": agent.unstake_available_at,
+        "registered_at": agent.registered_at,
+    })
+}
+
+pub fn task_json(address: &Pubkey, task: &Task) -> Value {
+    json!({
+        "address": key(address),
+        "id": task.id,
+        "client": key(&task.client),
+        "title": task.title,
+        "description": task.description,
+        "requirements": task.requirements,
+        "category": task.category,
+        "status": status(task.status),
+        "mode": mode(task.mode),
+        "mint": opt_key(task.mint),
+        "bounty_amount": task.bounty_amount,
+        "released_amount": task.released_amount,
+        "created_at": task.created_at,
+        "deadline": task.deadline,
+        "completed_at": task.completed_at,
+        "submission_count": task.submission_count,
+        "message_count": task.message_count,
+        "assigned_agent": opt_key(task.assigned_agent),
+        "winning_submission": opt_key(task.winning_submission),
+        "extension_requested": task.extension_requested,
+        "extension_hours": task.extension_hours,
+        "dispute_raised_by": opt_key(task.dispute_raised_by),
+        "rejection_count": task.rejection_count,
+        "judge": opt_key(task.judge),
+        "verdict": task.verdict.map(|verdict| json!({
+            "submission": key(&verdict.submission),
+            "rating": verdict.rating,
+            "issued_at": verdict.issued_at,
+        })),
+        "commit_reveal": task.commit_reveal,
+        "milestone_count": task.milestone_count,
+        "invite_only": task.invite_only,
+        "invited_agents": task.invited_agents.iter().map(key).collect::<Vec<_>>(),
+        "crowdfunded": task.crowdfunded,
+        "contributed": task.contributed,
+        "contributor_count": task.contributor_count,
+        "vote_leader": opt_key(task.vote_leader),
+        "refund_pool": task.refund_pool,
+        "min_stake": task.min_stake,
+        "payout_schedule": task.payout_schedule,
+    })
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(value) => value.clone(),
+        Value::Array(values) if values.is_empty() => "-".to_string(),
+        Value::Array(values) => values.iter().map(cell).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
+
+/// Print one object, as pretty JSON or as `key  value` rows
+pub fn print_object(format: Format, object: &Value) {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(object).unwrap()),
+        Format::Table => {
+            let Value::Object(fields) = object else {
+                return println!("{}", cell(object));
+            };
+            let width = fields.keys().map(String::len).max().unwrap_or(0);
+            for (name, value) in fields {
+                println!("{name:width$}  {}", cell(value));
+            }
+        }
+    }
+}
+
+/// Print a list of objects, as a JSON array or as a table of `columns`
+pub fn print_list(format: Format, objects: &[Value], columns: &[&str]) {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(objects).unwrap()),
+        Format::Table => {
+            let rows: Vec<Vec<String>> = objects
+                .iter()
+                .map(|object| columns.iter().map(|column| cell(&object[*column])).collect())
+                .collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0).max(column.len()))
+                .collect();
+            let line = |cells: Vec<String>| {
+                let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, width)| format!("{cell:width$}")).collect();
+                println!("{}", padded.join("  ").trim_end());
+            };
+            line(columns.iter().map(|column| column.to_string()).collect());
+            for row in rows {
+                line(row);
+            }
+        }
+    }
+}
+
+/// Print the result of a transaction: its signature plus any accounts it created
+pub fn print_sent(format: Format, fields: Vec<(&str, String)>) {
+    let object: Map<String, Value> = fields.into_iter().map(|(name, value)| (name.to_string(), Value::String(value))).collect();
+    print_object(format, &Value::Object(object));
+}
@@ -0,0 +1,1161 @@
+//! One builder per program instruction.
+//!
+//! Builders derive every PDA and, for SPL tasks, every associated token account (escrow, client,
+//! agent, funder and treasury ATAs) from the wallets passed in. Pass `spl: None` for SOL tasks.
+//! The escrow PDA depends on how the task was opened, so builders that move funds take it from
+//! `pda::task_escrow`.
+
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use openfourr::{accounts, instruction as ix, DisputeResolution, MilestoneInput, SlashTarget, TaskOptions, ID};
+
+use crate::{pda, SplMint};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction { program_id: ID, accounts: accounts.to_account_metas(None), data: data.data() }
+}
+
+fn mint(spl: Option<SplMint>) -> Option<Pubkey> {
+    spl.map(|spl| spl.mint)
+}
+
+fn token_program(spl: Option<SplMint>) -> Option<Pubkey> {
+    spl.map(|spl| spl.token_program)
+}
+
+fn ata(spl: Option<SplMint>, owner: &Pubkey) -> Option<Pubkey> {
+    spl.map(|spl| spl.ata(owner))
+}
+
+fn associated_token_program(spl: Option<SplMint>) -> Option<Pubkey> {
+    spl.map(|_| anchor_spl::associated_token::ID)
+}
+
+// ============ PLATFORM ============
+
+pub fn initialize(authority: &Pubkey, platform_fee_bps: u16) -> Instruction {
+    build(
+        accounts::Initialize {
+            platform: pda::platform(),
+            treasury: pda::treasury(),
+            authority: *authority,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::Initialize { platform_fee_bps },
+    )
+}
+
+fn update_platform(authority: &Pubkey) -> accounts::UpdatePlatform {
+    accounts::UpdatePlatform {
+        platform: pda::platform(),
+        authority: *authority,
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn set_retention_period(authority: &Pubkey, retention_period: i64) -> Instruction {
+    build(update_platform(authority), ix::SetRetentionPeriod { retention_period })
+}
+
+pub fn set_slash_bps(authority: &Pubkey, slash_bps: u16) -> Instruction {
+    build(update_platform(authority), ix::SetSlashBps { slash_bps })
+}
+
+pub fn set_dispute_bond(authority: &Pubkey, dispute_bond: u64) -> Instruction {
+    build(update_platform(authority), ix::SetDisputeBond { dispute_bond })
+}
+
+pub fn update_fee(authority: &Pubkey, fee_bps: u16) -> Instruction {
+    build(update_platform(authority), ix::UpdateFee { fee_bps })
+}
+
+pub fn propose_authority(authority: &Pubkey, new_authority: Pubkey) -> Instruction {
+    build(update_platform(authority), ix::ProposeAuthority { new_authority })
+}
+
+pub fn accept_authority(new_authority: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAuthority {
+            platform: pda::platform(),
+            new_authority: *new_authority,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::AcceptAuthority {},
+    )
+}
+
+pub fn set_paused(authority: &Pubkey, paused: bool) -> Instruction {
+    build(update_platform(authority), ix::SetPaused { paused })
+}
+
+pub fn set_arbiter(authority: &Pubkey, arbiter: Option<Pubkey>) -> Instruction {
+    build(update_platform(authority), ix::SetArbiter { arbiter })
+}
+
+pub fn set_judge(authority: &Pubkey, judge: Option<Pubkey>) -> Instruction {
+    build(update_platform(authority), ix::SetJudge { judge })
+}
+
+/// Withdraw treasury fees to `destination`; SPL fees go from the treasury ATA to `destination`'s ATA
+pub fn withdraw_fees(authority: &Pubkey, destination: &Pubkey, amount: u64, spl: Option<SplMint>) -> Instruction {
+    build(
+        accounts::WithdrawFees {
+            platform: pda::platform(),
+            treasury: pda::treasury(),
+            destination: *destination,
+            authority: *authority,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            treasury_token_account: ata(spl, &pda::treasury()),
+            destination_token_account: ata(spl, destination),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::WithdrawFees { amount },
+    )
+}
+
+// ============ AGENTS ============
+
+pub fn register_agent(owner: &Pubkey, name: String, bio: String, skills: Vec<String>, hourly_rate: u64) -> Instruction {
+    build(
+        accounts::RegisterAgent {
+            agent_profile: pda::agent(owner),
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RegisterAgent { name, bio, skills, hourly_rate },
+    )
+}
+
+fn update_agent_accounts(owner: &Pubkey) -> accounts::UpdateAgent {
+    accounts::UpdateAgent {
+        agent_profile: pda::agent(owner),
+        owner: *owner,
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn update_agent(owner: &Pubkey, name: String, bio: String, skills: Vec<String>, hourly_rate: u64) -> Instruction {
+    build(update_agent_accounts(owner), ix::UpdateAgent { name, bio, skills, hourly_rate })
+}
+
+pub fn set_agent_active(owner: &Pubkey, is_active: bool) -> Instruction {
+    build(update_agent_accounts(owner), ix::SetAgentActive { is_active })
+}
+
+pub fn request_unstake(owner: &Pubkey, amount: u64) -> Instruction {
+    build(update_agent_accounts(owner), ix::RequestUnstake { amount })
+}
+
+pub fn close_agent(owner: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseAgent {
+            agent_profile: pda::agent(owner),
+            stake_vault: pda::stake_vault(owner),
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseAgent {},
+    )
+}
+
+fn stake_accounts(owner: &Pubkey) -> accounts::Stake {
+    accounts::Stake {
+        agent_profile: pda::agent(owner),
+        stake_vault: pda::stake_vault(owner),
+        owner: *owner,
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn stake(owner: &Pubkey, amount: u64) -> Instruction {
+    build(stake_accounts(owner), ix::Stake { amount })
+}
+
+pub fn withdraw_stake(owner: &Pubkey) -> Instruction {
+    build(stake_accounts(owner), ix::WithdrawStake {})
+}
+
+// ============ TASKS ============
+
+/// Open a task at `pda::task(task_id)`; `task_id` must be the current `platform.total_tasks`
+#[allow(clippy::too_many_arguments)]
+pub fn create_task(
+    client: &Pubkey,
+    task_id: u64,
+    title: String,
+    description: String,
+    requirements: String,
+    category: String,
+    bounty_amount: u64,
+    deadline_hours: u64,
+    options: TaskOptions,
+    spl: Option<SplMint>,
+) -> Instruction {
+    let task = pda::task(task_id);
+    let escrow = pda::escrow(task_id);
+    build(
+        accounts::CreateTask {
+            task,
+            client_profile: pda::client_profile(client),
+            platform: pda::platform(),
+            escrow,
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            client_token_account: ata(spl, client),
+            escrow_token_account: ata(spl, &escrow),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CreateTask { title, description, requirements, category, bounty_amount, deadline_hours, options },
+    )
+}
+
+/// Open a task at `pda::client_task(client, task_nonce)`; `task_nonce` must be the client's
+/// current `ClientProfile::task_nonce` (0 before their first task)
+#[allow(clippy::too_many_arguments)]
+pub fn create_client_task(
+    client: &Pubkey,
+    task_nonce: u64,
+    title: String,
+    description: String,
+    requirements: String,
+    category: String,
+    bounty_amount: u64,
+    deadline_hours: u64,
+    options: TaskOptions,
+    spl: Option<SplMint>,
+) -> Instruction {
+    let task = pda::client_task(client, task_nonce);
+    let escrow = pda::client_escrow(&task);
+    build(
+        accounts::CreateClientTask {
+            client_profile: pda::client_profile(client),
+            task,
+            platform: pda::platform(),
+            escrow,
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            client_token_account: ata(spl, client),
+            escrow_token_account: ata(spl, &escrow),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CreateClientTask { title, description, requirements, category, bounty_amount, deadline_hours, options },
+    )
+}
+
+fn update_task_accounts(client: &Pubkey, task: &Pubkey, has_milestones: bool) -> accounts::UpdateTask {
+    accounts::UpdateTask {
+        task: *task,
+        client: *client,
+        milestone_plan: has_milestones.then(|| pda::milestone_plan(task)),
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+/// Pass `has_milestones` for tasks with a milestone plan, whose deadline can't move before the last milestone
+#[allow(clippy::too_many_arguments)]
+pub fn update_task(
+    client: &Pubkey,
+    task: &Pubkey,
+    title: String,
+    description: String,
+    requirements: String,
+    category: String,
+    deadline_hours: u64,
+    has_milestones: bool,
+) -> Instruction {
+    build(
+        update_task_accounts(client, task, has_milestones),
+        ix::UpdateTask { title, description, requirements, category, deadline_hours },
+    )
+}
+
+pub fn set_invited_agents(client: &Pubkey, task: &Pubkey, invited_agents: Vec<Pubkey>) -> Instruction {
+    build(update_task_accounts(client, task, false), ix::SetInvitedAgents { invited_agents })
+}
+
+pub fn decline_invite(agent: &Pubkey, task: &Pubkey) -> Instruction {
+    build(
+        accounts::DeclineInvite {
+            task: *task,
+            agent_owner: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::DeclineInvite {},
+    )
+}
+
+pub fn top_up_bounty(client: &Pubkey, task: &Pubkey, escrow: &Pubkey, amount: u64, spl: Option<SplMint>) -> Instruction {
+    build(
+        accounts::TopUpBounty {
+            task: *task,
+            escrow: *escrow,
+            platform: pda::platform(),
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::TopUpBounty { amount },
+    )
+}
+
+/// Cancel a task; pass `has_milestones` for tasks with a milestone plan
+pub fn cancel_task(
+    client: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    has_milestones: bool,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::CancelTask {
+            task: *task,
+            client_profile: pda::client_profile(client),
+            escrow: *escrow,
+            client: *client,
+            milestone_plan: has_milestones.then(|| pda::milestone_plan(task)),
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CancelTask {},
+    )
+}
+
+/// Refund a task 7 days past its deadline; `payer` can be any wallet
+pub fn auto_refund_expired(
+    payer: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    client: &Pubkey,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::AutoRefundExpired {
+            task: *task,
+            client_profile: pda::client_profile(client),
+            escrow: *escrow,
+            client: *client,
+            payer: *payer,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::AutoRefundExpired {},
+    )
+}
+
+pub fn request_extension(agent: &Pubkey, task: &Pubkey, extra_hours: u64) -> Instruction {
+    build(
+        accounts::RequestExtension {
+            task: *task,
+            submission: pda::submission(task, agent),
+            agent_owner: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RequestExtension { extra_hours },
+    )
+}
+
+fn approve_extension_accounts(client: &Pubkey, task: &Pubkey) -> accounts::ApproveExtension {
+    accounts::ApproveExtension {
+        task: *task,
+        client: *client,
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn approve_extension(client: &Pubkey, task: &Pubkey) -> Instruction {
+    build(approve_extension_accounts(client, task), ix::ApproveExtension {})
+}
+
+pub fn deny_extension(client: &Pubkey, task: &Pubkey) -> Instruction {
+    build(approve_extension_accounts(client, task), ix::DenyExtension {})
+}
+
+pub fn add_milestones(client: &Pubkey, task: &Pubkey, milestones: Vec<MilestoneInput>) -> Instruction {
+    build(
+        accounts::AddMilestones {
+            milestone_plan: pda::milestone_plan(task),
+            task: *task,
+            client: *client,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::AddMilestones { milestones },
+    )
+}
+
+/// Release the next milestone to `agent`; `releaser` is the client or the task judge
+#[allow(clippy::too_many_arguments)]
+pub fn release_milestone(
+    releaser: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    client: &Pubkey,
+    agent: &Pubkey,
+    index: u8,
+    rating: Option<u8>,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::ReleaseMilestone {
+            task: *task,
+            milestone_plan: pda::milestone_plan(task),
+            submission: pda::submission(task, agent),
+            agent_profile: pda::agent(agent),
+            agent_wallet: *agent,
+            escrow: *escrow,
+            platform: pda::platform(),
+            platform_treasury: pda::treasury(),
+            client: *client,
+            client_profile: pda::client_profile(client),
+            releaser: *releaser,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            agent_token_account: ata(spl, agent),
+            treasury_token_account: ata(spl, &pda::treasury()),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::ReleaseMilestone { index, rating },
+    )
+}
+
+pub fn close_task(task: &Pubkey, client: &Pubkey, has_milestones: bool) -> Instruction {
+    build(
+        accounts::CloseTask {
+            task: *task,
+            client: *client,
+            milestone_plan: has_milestones.then(|| pda::milestone_plan(task)),
+            platform: pda::platform(),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseTask {},
+    )
+}
+
+// ============ SUBMISSIONS ============
+
+fn submit_application_accounts(agent: &Pubkey, task: &Pubkey) -> accounts::SubmitApplication {
+    accounts::SubmitApplication {
+        submission: pda::submission(task, agent),
+        task: *task,
+        agent_profile: pda::agent(agent),
+        platform: pda::platform(),
+        agent_owner: *agent,
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn submit_application(
+    agent: &Pubkey,
+    task: &Pubkey,
+    submission_url: String,
+    submission_notes: String,
+) -> Instruction {
+    build(
+        submit_application_accounts(agent, task),
+        ix::SubmitApplication { submission_url, submission_notes },
+    )
+}
+
+/// Commit to a sealed submission; see `openfourr::submission_commitment` for the hash
+pub fn commit_submission(agent: &Pubkey, task: &Pubkey, commitment: [u8; 32]) -> Instruction {
+    build(submit_application_accounts(agent, task), ix::CommitSubmission { commitment })
+}
+
+pub fn reveal_submission(
+    agent: &Pubkey,
+    task: &Pubkey,
+    submission_url: String,
+    submission_notes: String,
+    salt: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::RevealSubmission {
+            submission: pda::submission(task, agent),
+            task: *task,
+            agent_owner: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RevealSubmission { submission_url, submission_notes, salt },
+    )
+}
+
+pub fn claim_task(agent: &Pubkey, task: &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimTask {
+            submission: pda::submission(task, agent),
+            task: *task,
+            agent_profile: pda::agent(agent),
+            platform: pda::platform(),
+            agent_owner: *agent,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::ClaimTask {},
+    )
+}
+
+pub fn submit_work(agent: &Pubkey, task: &Pubkey, submission_url: String, submission_notes: String) -> Instruction {
+    build(
+        accounts::SubmitWork {
+            submission: pda::submission(task, agent),
+            task: *task,
+            agent_owner: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::SubmitWork { submission_url, submission_notes },
+    )
+}
+
+pub fn reject_work(client: &Pubkey, task: &Pubkey, agent: &Pubkey, reason: String) -> Instruction {
+    build(
+        accounts::RejectWork {
+            task: *task,
+            submission: pda::submission(task, agent),
+            agent_profile: pda::agent(agent),
+            client: *client,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RejectWork { reason },
+    )
+}
+
+/// Send message `message_id` (the task's current `message_count`); agents pass their submission
+pub fn send_message(
+    sender: &Pubkey,
+    task: &Pubkey,
+    message_id: u64,
+    content: String,
+    submission: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::SendMessage {
+            message: pda::message(task, message_id),
+            task: *task,
+            submission,
+            sender: *sender,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::SendMessage { content },
+    )
+}
+
+// ============ BIDS ============
+
+pub fn submit_bid(agent: &Pubkey, task: &Pubkey, price: u64, eta_hours: u64) -> Instruction {
+    build(
+        accounts::SubmitBid {
+            bid: pda::bid(task, agent),
+            task: *task,
+            agent_profile: pda::agent(agent),
+            platform: pda::platform(),
+            agent_owner: *agent,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::SubmitBid { price, eta_hours },
+    )
+}
+
+pub fn withdraw_bid(agent: &Pubkey, task: &Pubkey) -> Instruction {
+    build(
+        accounts::WithdrawBid {
+            bid: pda::bid(task, agent),
+            task: *task,
+            agent_owner: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::WithdrawBid {},
+    )
+}
+
+/// Close a losing bid once the task is assigned or finished, returning its rent to `agent`
+pub fn close_bid(task: &Pubkey, agent: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseBid {
+            bid: pda::bid(task, agent),
+            task: *task,
+            agent_wallet: *agent,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseBid {},
+    )
+}
+
+pub fn accept_bid(client: &Pubkey, task: &Pubkey, escrow: &Pubkey, agent: &Pubkey, spl: Option<SplMint>) -> Instruction {
+    build(
+        accounts::AcceptBid {
+            task: *task,
+            bid: pda::bid(task, agent),
+            agent_wallet: *agent,
+            escrow: *escrow,
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::AcceptBid {},
+    )
+}
+
+// ============ PAYOUTS ============
+
+fn select_winner_accounts(
+    client: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    agent: &Pubkey,
+    spl: Option<SplMint>,
+) -> accounts::SelectWinner {
+    accounts::SelectWinner {
+        task: *task,
+        submission: pda::submission(task, agent),
+        agent_profile: pda::agent(agent),
+        agent_wallet: *agent,
+        escrow: *escrow,
+        platform: pda::platform(),
+        platform_treasury: pda::treasury(),
+        client_profile: pda::client_profile(client),
+        client: *client,
+        system_program: system_program::ID,
+        mint: mint(spl),
+        escrow_token_account: ata(spl, escrow),
+        agent_token_account: ata(spl, agent),
+        treasury_token_account: ata(spl, &pda::treasury()),
+        client_token_account: ata(spl, client),
+        token_program: token_program(spl),
+        associated_token_program: associated_token_program(spl),
+        event_authority: pda::event_authority(),
+        program: ID,
+    }
+}
+
+pub fn select_winner(
+    client: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    agent: &Pubkey,
+    rating: u8,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(select_winner_accounts(client, task, escrow, agent, spl), ix::SelectWinner { rating })
+}
+
+pub fn approve_work(
+    client: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    agent: &Pubkey,
+    rating: u8,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(select_winner_accounts(client, task, escrow, agent, spl), ix::ApproveWork { rating })
+}
+
+/// Pay `winners` (agent wallets, best place first) by the task's payout schedule; unfilled places are refunded
+pub fn select_winners(
+    client: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    winners: &[Pubkey],
+    ratings: Vec<u8>,
+    spl: Option<SplMint>,
+) -> Instruction {
+    let mut instruction = build(
+        accounts::SelectWinners {
+            task: *task,
+            escrow: *escrow,
+            platform: pda::platform(),
+            platform_treasury: pda::treasury(),
+            client_profile: pda::client_profile(client),
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            treasury_token_account: ata(spl, &pda::treasury()),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::SelectWinners { ratings },
+    );
+    for agent in winners {
+        instruction.accounts.push(AccountMeta::new(pda::submission(task, agent), false));
+        instruction.accounts.push(AccountMeta::new(pda::agent(agent), false));
+        instruction.accounts.push(AccountMeta::new(*agent, false));
+        if let Some(agent_token_account) = ata(spl, agent) {
+            instruction.accounts.push(AccountMeta::new(agent_token_account, false));
+        }
+    }
+    instruction
+}
+
+/// Approve or reject `agent`'s submission on a judged task; rejections slash stake to `client`
+#[allow(clippy::too_many_arguments)]
+pub fn judge_verdict(
+    judge: &Pubkey,
+    task: &Pubkey,
+    client: &Pubkey,
+    agent: &Pubkey,
+    approved: bool,
+    rating: u8,
+    reason: String,
+) -> Instruction {
+    build(
+        accounts::JudgeVerdict {
+            task: *task,
+            submission: pda::submission(task, agent),
+            agent_profile: pda::agent(agent),
+            platform: pda::platform(),
+            stake_vault: pda::stake_vault(agent),
+            client: *client,
+            judge: *judge,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::JudgeVerdict { approved, rating, reason },
+    )
+}
+
+/// Release a judged task's escrow once the challenge window has passed; `payer` can be any wallet
+pub fn finalize_verdict(
+    payer: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    client: &Pubkey,
+    agent: &Pubkey,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::FinalizeVerdict {
+            task: *task,
+            submission: pda::submission(task, agent),
+            agent_profile: pda::agent(agent),
+            agent_wallet: *agent,
+            escrow: *escrow,
+            platform: pda::platform(),
+            platform_treasury: pda::treasury(),
+            client: *client,
+            client_profile: pda::client_profile(client),
+            payer: *payer,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            agent_token_account: ata(spl, agent),
+            treasury_token_account: ata(spl, &pda::treasury()),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::FinalizeVerdict {},
+    )
+}
+
+// ============ CROWDFUNDING ============
+
+pub fn contribute(funder: &Pubkey, task: &Pubkey, escrow: &Pubkey, amount: u64, spl: Option<SplMint>) -> Instruction {
+    build(
+        accounts::Contribute {
+            contribution: pda::contribution(task, funder),
+            task: *task,
+            escrow: *escrow,
+            platform: pda::platform(),
+            funder: *funder,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            funder_token_account: ata(spl, funder),
+            token_program: token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::Contribute { amount },
+    )
+}
+
+/// Back `agent`'s submission as the winner
+pub fn vote_winner(funder: &Pubkey, task: &Pubkey, agent: &Pubkey) -> Instruction {
+    build(
+        accounts::VoteWinner {
+            contribution: pda::contribution(task, funder),
+            task: *task,
+            submission: pda::submission(task, agent),
+            funder: *funder,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::VoteWinner {},
+    )
+}
+
+/// Refund `funder`'s contribution to a finished crowdfunded task; `payer` can be any wallet
+pub fn claim_refund(
+    payer: &Pubkey,
+    funder: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    client: &Pubkey,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::ClaimRefund {
+            contribution: pda::contribution(task, funder),
+            task: *task,
+            escrow: *escrow,
+            funder: *funder,
+            payer: *payer,
+            client: *client,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            funder_token_account: ata(spl, funder),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::ClaimRefund {},
+    )
+}
+
+// ============ DISPUTES ============
+
+/// Dispute a task; agents pass their submission, the client passes `None`
+pub fn raise_dispute(
+    raiser: &Pubkey,
+    task: &Pubkey,
+    reason: String,
+    evidence_uri: String,
+    submission: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::RaiseDispute {
+            task: *task,
+            dispute: pda::dispute(task),
+            submission,
+            platform: pda::platform(),
+            raiser: *raiser,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RaiseDispute { reason, evidence_uri },
+    )
+}
+
+/// Resolve a dispute (by authority or arbiter); `agent` is required unless refunding the client
+/// in full without slashing
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_dispute(
+    arbiter: &Pubkey,
+    task: &Pubkey,
+    escrow: &Pubkey,
+    client: &Pubkey,
+    raiser: &Pubkey,
+    agent: Option<Pubkey>,
+    resolution: DisputeResolution,
+    rating: Option<u8>,
+    slash: Option<SlashTarget>,
+    return_bond: bool,
+    spl: Option<SplMint>,
+) -> Instruction {
+    build(
+        accounts::ResolveDispute {
+            task: *task,
+            dispute: pda::dispute(task),
+            raiser: *raiser,
+            submission: agent.map(|agent| pda::submission(task, &agent)),
+            agent_profile: agent.map(|agent| pda::agent(&agent)),
+            agent_wallet: agent,
+            stake_vault: agent.filter(|_| slash.is_some()).map(|agent| pda::stake_vault(&agent)),
+            escrow: *escrow,
+            client: *client,
+            client_profile: pda::client_profile(client),
+            platform: pda::platform(),
+            platform_treasury: pda::treasury(),
+            arbiter: *arbiter,
+            system_program: system_program::ID,
+            mint: mint(spl),
+            escrow_token_account: ata(spl, escrow),
+            agent_token_account: agent.and_then(|agent| ata(spl, &agent)),
+            treasury_token_account: ata(spl, &pda::treasury()),
+            client_token_account: ata(spl, client),
+            token_program: token_program(spl),
+            associated_token_program: associated_token_program(spl),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::ResolveDispute { resolution, rating, slash, return_bond },
+    )
+}
+
+// ============ CLOSING ============
+
+/// Close `agent`'s submission on a finished task; pass `with_agent_profile` while it is still pending
+pub fn close_submission(task: &Pubkey, agent: &Pubkey, with_agent_profile: bool) -> Instruction {
+    build(
+        accounts::CloseSubmission {
+            task: *task,
+            submission: pda::submission(task, agent),
+            agent_profile: with_agent_profile.then(|| pda::agent(agent)),
+            agent_wallet: *agent,
+            platform: pda::platform(),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseSubmission {},
+    )
+}
+
+/// Mark the submissions of `agents` `NotSelected`, closing them when `close` is set
+pub fn finalize_submissions(task: &Pubkey, agents: &[Pubkey], close: bool) -> Instruction {
+    let mut instruction = build(
+        accounts::FinalizeSubmissions {
+            task: *task,
+            platform: pda::platform(),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::FinalizeSubmissions { close },
+    );
+    for agent in agents {
+        instruction.accounts.push(AccountMeta::new(pda::submission(task, agent), false));
+        instruction.accounts.push(AccountMeta::new(pda::agent(agent), false));
+        instruction.accounts.push(AccountMeta::new(*agent, false));
+    }
+    instruction
+}
+
+pub fn close_message(task: &Pubkey, message_id: u64, sender: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseMessage {
+            task: *task,
+            message: pda::message(task, message_id),
+            sender: *sender,
+            platform: pda::platform(),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseMessage {},
+    )
+}
+
+/// Close several messages at once; `messages` are `(message_id, sender)` pairs
+pub fn close_messages(task: &Pubkey, messages: &[(u64, Pubkey)]) -> Instruction {
+    let mut instruction = build(
+        accounts::CloseMessages {
+            task: *task,
+            platform: pda::platform(),
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::CloseMessages {},
+    );
+    for (message_id, sender) in messages {
+        instruction.accounts.push(AccountMeta::new(pda::message(task, *message_id), false));
+        instruction.accounts.push(AccountMeta::new(*sender, false));
+    }
+    instruction
+}
+
+// ============ REPUTATION ============
+
+pub fn rate_client(agent: &Pubkey, task: &Pubkey, client: &Pubkey, rating: u8) -> Instruction {
+    build(
+        accounts::RateClient {
+            task: *task,
+            submission: pda::submission(task, agent),
+            client_profile: pda::client_profile(client),
+            agent_owner: *agent,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::RateClient { rating },
+    )
+}
+
+// ============ MIGRATIONS ============
+
+/// Rewrite the platform opened by the first devnet deployment; `payer` covers the extra rent
+pub fn migrate_platform(payer: &Pubkey) -> Instruction {
+    build(
+        accounts::MigratePlatform {
+            platform: pda::platform(),
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::MigratePlatform {},
+    )
+}
+
+/// Migrate a v0 task, which is always keyed by id; `pda::escrow(task_id)` gets topped up to its rent reserve
+pub fn migrate_task(payer: &Pubkey, task_id: u64) -> Instruction {
+    build(
+        accounts::MigrateTask {
+            task: pda::task(task_id),
+            escrow: pda::escrow(task_id),
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::MigrateTask {},
+    )
+}
+
+pub fn migrate_agent(payer: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateAgent {
+            agent_profile: pda::agent(owner),
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::MigrateAgent {},
+    )
+}
+
+/// Migrate `agent`'s submission once both the task and the agent profile are migrated
+pub fn migrate_submission(payer: &Pubkey, task: &Pubkey, agent: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateSubmission {
+            submission: pda::submission(task, agent),
+            task: *task,
+            agent_profile: pda::agent(agent),
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::MigrateSubmission {},
+    )
+}
+
+/// Migrate message `message_id` once its task is migrated
+pub fn migrate_message(payer: &Pubkey, task: &Pubkey, message_id: u64) -> Instruction {
+    build(
+        accounts::MigrateMessage {
+            message: pda::message(task, message_id),
+            task: *task,
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: ID,
+        },
+        ix::MigrateMessage {},
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builders_mark_signers_and_append_event_accounts() {
+        let client = Pubkey::new_unique();
+        let task = pda::task(0);
+        let instruction = cancel_task(&client, &task, &pda::escrow(0), false, None);
+
+        assert_eq!(instruction.program_id, ID);
+        let signers: Vec<_> = instruction.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, vec![client]);
+
+        let tail: Vec<_> = instruction.accounts.iter().rev().take(2).map(|meta| meta.pubkey).collect();
+        assert_eq!(tail, vec![ID, pda::event_authority()]);
+    }
+
+    #[test]
+    fn select_winners_appends_one_group_per_winner() {
+        let client = Pubkey::new_unique();
+        let task = pda::task(7);
+        let escrow = pda::escrow(7);
+        let winners = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let base = select_winners(&client, &task, &escrow, &[], vec![], None).accounts.len();
+
+        let sol = select_winners(&client, &task, &escrow, &winners, vec![5, 4], None);
+        assert_eq!(sol.accounts.len(), base + 2 * 3);
+        assert_eq!(sol.accounts[base].pubkey, pda::submission(&task, &winners[0]));
+
+        let spl = SplMint::new(Pubkey::new_unique(), Pubkey::new_unique());
+        let with_tokens = select_winners(&client, &task, &escrow, &winners, vec![5, 4], Some(spl));
+        assert_eq!(with_tokens.accounts.len(), base + 2 * 4);
+        assert_eq!(with_tokens.accounts[base + 3].pubkey, spl.ata(&winners[0]));
+    }
+}
@@ -0,0 +1,42 @@
+//! Rust client for the Openfourr program.
+//!
+//! - [`pda`] derives every program address from its seeds
+//! - [`instructions`] builds one [`Instruction`] per program instruction, deriving PDAs and
+//!   token accounts so callers only pass wallets, task keys and arguments
+//! - [`state`] decodes `Task`, `AgentProfile`, `Submission`, `Message` and `Platform` accounts
+//!
+//! Builders don't touch the network; read whatever state they need (e.g. `platform.total_tasks`
+//! for [`instructions::create_task`]) with [`state`] first.
+
+pub mod instructions;
+pub mod pda;
+pub mod state;
+
+pub use anchor_lang::solana_program::instruction::Instruction;
+pub use anchor_lang::solana_program::pubkey::Pubkey;
+pub use openfourr::{
+    self, AgentProfile, Bid, ClientProfile, Contribution, Dispute, DisputeResolution, Message, Milestone,
+    MilestoneInput, MilestonePlan, Platform, SlashTarget, Submission, SubmissionStatus, Task, TaskMode, TaskOptions,
+    TaskStatus, ID,
+};
+
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+/// Mint and token program of an SPL bounty; builders take `None` for native SOL tasks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplMint {
+    pub mint: Pubkey,
+    /// Token or Token-2022 program owning `mint`
+    pub token_program: Pubkey,
+}
+
+impl SplMint {
+    pub fn new(mint: Pubkey, token_program: Pubkey) -> Self {
+        Self { mint, token_program }
+    }
+
+    /// Associated token account of `owner` for this mint
+    pub fn ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint, &self.token_program)
+    }
+}
@@ -0,0 +1,88 @@
+//! Program-derived addresses, mirroring the `seeds` in the program's account contexts
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+use openfourr::{Task, ID};
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &ID).0
+}
+
+pub fn platform() -> Pubkey {
+    find(&[b"platform"])
+}
+
+/// Lamport treasury holding collected fees
+pub fn treasury() -> Pubkey {
+    find(&[b"treasury"])
+}
+
+pub fn agent(owner: &Pubkey) -> Pubkey {
+    find(&[b"agent", owner.as_ref()])
+}
+
+/// Lamport vault holding an agent's stake
+pub fn stake_vault(owner: &Pubkey) -> Pubkey {
+    find(&[b"stake", owner.as_ref()])
+}
+
+pub fn client_profile(client: &Pubkey) -> Pubkey {
+    find(&[b"client", client.as_ref()])
+}
+
+/// Task opened by `create_task`, keyed by `platform.total_tasks`
+pub fn task(task_id: u64) -> Pubkey {
+    find(&[b"task", task_id.to_le_bytes().as_ref()])
+}
+
+/// Task opened by `create_client_task`, keyed by the client's `task_nonce`
+pub fn client_task(client: &Pubkey, task_nonce: u64) -> Pubkey {
+    find(&[b"task", client.as_ref(), task_nonce.to_le_bytes().as_ref()])
+}
+
+/// Escrow of a `create_task` task, keyed by its id
+pub fn escrow(task_id: u64) -> Pubkey {
+    find(&[b"escrow", task_id.to_le_bytes().as_ref()])
+}
+
+/// Escrow of a `create_client_task` task, keyed by the task address
+pub fn client_escrow(task: &Pubkey) -> Pubkey {
+    find(&[b"escrow", task.as_ref()])
+}
+
+/// Escrow of the task at `address`, whichever way it was opened
+pub fn task_escrow(address: &Pubkey, task: &Task) -> Pubkey {
+    if task.per_client {
+        client_escrow(address)
+    } else {
+        escrow(task.id)
+    }
+}
+
+pub fn submission(task: &Pubkey, agent: &Pubkey) -> Pubkey {
+    find(&[b"submission", task.as_ref(), agent.as_ref()])
+}
+
+pub fn message(task: &Pubkey, message_id: u64) -> Pubkey {
+    find(&[b"message", task.as_ref(), message_id.to_le_bytes().as_ref()])
+}
+
+pub fn milestone_plan(task: &Pubkey) -> Pubkey {
+    find(&[b"milestones", task.as_ref()])
+}
+
+pub fn bid(task: &Pubkey, agent: &Pubkey) -> Pubkey {
+    find(&[b"bid", task.as_ref(), agent.as_ref()])
+}
+
+pub fn contribution(task: &Pubkey, funder: &Pubkey) -> Pubkey {
+    find(&[b"contribution", task.as_ref(), funder.as_ref()])
+}
+
+pub fn dispute(task: &Pubkey) -> Pubkey {
+    find(&[b"dispute", task.as_ref()])
+}
+
+/// Signer of the program's `emit_cpi!` self-invocations
+pub fn event_authority() -> Pubkey {
+    find(&[b"__event_authority"])
+}
@@ -0,0 +1,29 @@
+//! Account decoders; each checks the account discriminator before deserializing
+
+use anchor_lang::{AccountDeserialize, Result};
+use openfourr::{AgentProfile, Message, Platform, Submission, Task};
+
+/// Decode any program account from its raw data, discriminator included
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_task(data: &[u8]) -> Result<Task> {
+    decode(data)
+}
+
+pub fn decode_agent_profile(data: &[u8]) -> Result<AgentProfile> {
+    decode(data)
+}
+
+pub fn decode_submission(data: &[u8]) -> Result<Submission> {
+    decode(data)
+}
+
+pub fn decode_message(data: &[u8]) -> Result<Message> {
+    decode(data)
+}
+
+pub fn decode_platform(data: &[u8]) -> Result<Platform> {
+    decode(data)
+}
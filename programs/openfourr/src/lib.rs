@@ -1,12 +1,66 @@
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id, AssociatedToken};
+// `solana_program::hash` is the same sha256 hasher, reached through the ATA crate's re-export
+use anchor_spl::associated_token::spl_associated_token_account::solana_program::hash::hashv;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+use std::slice::ChunksExact;
 
 declare_id!("FBtigfHS7NXnQYgjaGACFY8SVmd3sX2XmsdWna2ak99L");
 
+/// Highest platform fee the authority can set (10%)
+pub const MAX_FEE_BPS: u16 = 1000;
+
+/// Rejections an exclusive-claim task tolerates before it is marked `Rejected`
+pub const MAX_REJECTIONS: u8 = 3;
+
+/// Seconds a client has to dispute a judge's approval before escrow is released
+pub const JUDGE_CHALLENGE_WINDOW: i64 = 24 * 3600;
+
+/// Seconds after the deadline during which sealed submissions can be revealed
+pub const REVEAL_WINDOW: i64 = 48 * 3600;
+
+/// Bounds on how many milestones a task can be split into
+pub const MIN_MILESTONES: usize = 2;
+pub const MAX_MILESTONES: usize = 10;
+
+/// Most agents a client can invite to an invite-only task
+pub const MAX_INVITED_AGENTS: usize = 10;
+
+/// Lamports a new platform charges as a dispute bond (0.01 SOL)
+pub const DEFAULT_DISPUTE_BOND: u64 = 10_000_000;
+
+/// Most places a multi-winner payout schedule can have
+pub const MAX_WINNERS: usize = 5;
+
+/// Seconds between requesting an unstake and withdrawing it
+pub const UNSTAKE_COOLDOWN: i64 = 7 * 24 * 3600;
+
+/// Seconds a winning submission stays open for `rate_client` after the task completes, whatever the retention period
+pub const CLIENT_RATING_WINDOW: i64 = 7 * 24 * 3600;
+
+/// Byte limits for text fields; the account `#[max_len]`s use the same values
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_BIO_LEN: usize = 500;
+pub const MAX_SKILLS: usize = 10;
+pub const MAX_SKILL_LEN: usize = 32;
+pub const MAX_TITLE_LEN: usize = 100;
+pub const MAX_DESCRIPTION_LEN: usize = 2000;
+pub const MAX_REQUIREMENTS_LEN: usize = 1000;
+pub const MAX_CATEGORY_LEN: usize = 32;
+pub const MAX_URL_LEN: usize = 500;
+pub const MAX_NOTES_LEN: usize = 1000;
+pub const MAX_MESSAGE_LEN: usize = 500;
+pub const MAX_MILESTONE_TITLE_LEN: usize = 64;
+pub const MAX_REASON_LEN: usize = 500;
+pub const MAX_EVIDENCE_URI_LEN: usize = 200;
+
 #[program]
 pub mod openfourr {
     use super::*;
 
     pub fn initialize(ctx: Context<Initialize>, platform_fee_bps: u16) -> Result<()> {
+        require!(platform_fee_bps <= MAX_FEE_BPS, OpenfourrError::InvalidFee);
+
         let platform = &mut ctx.accounts.platform;
         platform.authority = ctx.accounts.authority.key();
         platform.fee_bps = platform_fee_bps;
@@ -14,6 +68,192 @@ pub mod openfourr {
         platform.total_completed = 0;
         platform.total_volume = 0;
         platform.bump = ctx.bumps.platform;
+        platform.arbiter = None;
+        platform.judge = None;
+        platform.pending_authority = None;
+        platform.paused = false;
+        platform.treasury_bump = ctx.bumps.treasury;
+        platform.total_fees_collected = 0;
+        platform.retention_period = 0;
+        platform.slash_bps = 0;
+        platform.dispute_bond = DEFAULT_DISPUTE_BOND;
+
+        // Fund the treasury PDA up to rent exemption so small fees can land in it
+        let rent_exempt = Rent::get()?.minimum_balance(0);
+        let treasury_balance = ctx.accounts.treasury.lamports();
+        if treasury_balance < rent_exempt {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, rent_exempt.saturating_sub(treasury_balance))?;
+        }
+
+        emit_cpi!(PlatformInitialized {
+            authority: platform.authority,
+            fee_bps: platform.fee_bps,
+            dispute_bond: platform.dispute_bond,
+        });
+        Ok(())
+    }
+
+    /// Set how long finished tasks are kept before their accounts can be closed (by platform authority)
+    pub fn set_retention_period(ctx: Context<UpdatePlatform>, retention_period: i64) -> Result<()> {
+        require!(retention_period >= 0, OpenfourrError::InvalidRetentionPeriod);
+
+        let platform = &mut ctx.accounts.platform;
+        platform.retention_period = retention_period;
+
+        emit_cpi!(RetentionPeriodUpdated { retention_period });
+        Ok(())
+    }
+
+    /// Withdraw collected fees from the treasury PDA (by platform authority)
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let platform = &ctx.accounts.platform;
+        let treasury = &ctx.accounts.treasury;
+
+        require!(amount > 0, OpenfourrError::InvalidAmount);
+
+        let treasury_seeds = &[b"treasury".as_ref(), &[platform.treasury_bump]];
+        let signer_seeds = &[&treasury_seeds[..]];
+
+        let mint = match ctx.accounts.mint.as_ref() {
+            Some(mint) => {
+                let treasury_token_account = ctx.accounts.treasury_token_account.as_ref().ok_or(OpenfourrError::TokenAccountRequired)?;
+                let destination_token_account = ctx.accounts.destination_token_account.as_ref().ok_or(OpenfourrError::TokenAccountRequired)?;
+                let token_program = ctx.accounts.token_program.as_ref().ok_or(OpenfourrError::TokenAccountRequired)?;
+                let associated_token_program = ctx.accounts.associated_token_program.as_ref().ok_or(OpenfourrError::TokenAccountRequired)?;
+
+                require_keys_eq!(treasury_token_account.owner, treasury.key(), OpenfourrError::TokenAccountOwnerMismatch);
+                require_keys_eq!(treasury_token_account.mint, mint.key(), OpenfourrError::MintMismatch);
+                open_token_account(
+                    destination_token_account,
+                    &ctx.accounts.destination,
+                    mint,
+                    &ctx.accounts.authority,
+                    &ctx.accounts.system_program,
+                    token_program,
+                    associated_token_program,
+                )?;
+
+                let cpi_context = CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: treasury_token_account.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: destination_token_account.to_account_info(),
+                        authority: treasury.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                token_interface::transfer_checked(cpi_context, amount, mint.decimals)?;
+                Some(mint.key())
+            }
+            None => {
+                let withdrawable = treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+                require!(amount <= withdrawable, OpenfourrError::InsufficientTreasury);
+
+                let cpi_context = CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: treasury.to_account_info(),
+                        to: ctx.accounts.destination.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)?;
+                None
+            }
+        };
+
+        emit_cpi!(FeesWithdrawn { destination: ctx.accounts.destination.key(), amount, mint });
+        Ok(())
+    }
+
+    /// Set the share of an agent's stake slashed on judge rejections and disputes (by platform authority)
+    pub fn set_slash_bps(ctx: Context<UpdatePlatform>, slash_bps: u16) -> Result<()> {
+        require!(slash_bps <= 10000, OpenfourrError::InvalidSlash);
+
+        let platform = &mut ctx.accounts.platform;
+        platform.slash_bps = slash_bps;
+
+        emit_cpi!(SlashRateUpdated { slash_bps });
+        Ok(())
+    }
+
+    /// Set the bond a participant locks when raising a dispute (by platform authority)
+    pub fn set_dispute_bond(ctx: Context<UpdatePlatform>, dispute_bond: u64) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.dispute_bond = dispute_bond;
+
+        emit_cpi!(DisputeBondUpdated { dispute_bond });
+        Ok(())
+    }
+
+    /// Update the platform fee (by platform authority)
+    pub fn update_fee(ctx: Context<UpdatePlatform>, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, OpenfourrError::InvalidFee);
+
+        let platform = &mut ctx.accounts.platform;
+        let old_fee_bps = platform.fee_bps;
+        platform.fee_bps = fee_bps;
+
+        emit_cpi!(FeeUpdated { old_fee_bps, new_fee_bps: fee_bps });
+        Ok(())
+    }
+
+    /// Propose a new platform authority (by platform authority)
+    pub fn propose_authority(ctx: Context<UpdatePlatform>, new_authority: Pubkey) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.pending_authority = Some(new_authority);
+
+        emit_cpi!(AuthorityProposed { current: platform.authority, proposed: new_authority });
+        Ok(())
+    }
+
+    /// Accept a proposed authority handover (by proposed authority)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let new_authority = ctx.accounts.new_authority.key();
+
+        require!(platform.pending_authority == Some(new_authority), OpenfourrError::NotPendingAuthority);
+
+        let previous = platform.authority;
+        platform.authority = new_authority;
+        platform.pending_authority = None;
+
+        emit_cpi!(AuthorityTransferred { previous, new_authority });
+        Ok(())
+    }
+
+    /// Pause or resume task creation, submissions and payouts (by platform authority)
+    pub fn set_paused(ctx: Context<UpdatePlatform>, paused: bool) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.paused = paused;
+
+        emit_cpi!(PauseToggled { paused });
+        Ok(())
+    }
+
+    /// Set or clear the dispute arbiter (by platform authority)
+    pub fn set_arbiter(ctx: Context<UpdatePlatform>, arbiter: Option<Pubkey>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.arbiter = arbiter;
+
+        emit_cpi!(ArbiterUpdated { arbiter });
+        Ok(())
+    }
+
+    /// Set or clear the AI judge authority (by platform authority)
+    pub fn set_judge(ctx: Context<UpdatePlatform>, judge: Option<Pubkey>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.judge = judge;
+
+        emit_cpi!(JudgeUpdated { judge });
         Ok(())
     }
 
@@ -24,9 +264,7 @@ pub mod openfourr {
         skills: Vec<String>,
         hourly_rate: u64,
     ) -> Result<()> {
-        require!(name.len() <= 32, OpenfourrError::NameTooLong);
-        require!(bio.len() <= 500, OpenfourrError::BioTooLong);
-        require!(skills.len() <= 10, OpenfourrError::TooManySkills);
+        validate_agent_profile(&name, &bio, &skills)?;
 
         let agent = &mut ctx.accounts.agent_profile;
         agent.owner = ctx.accounts.owner.key();
@@ -42,9 +280,167 @@ pub mod openfourr {
         agent.registered_at = Clock::get()?.unix_timestamp;
         agent.is_active = true;
         agent.bump = ctx.bumps.agent_profile;
+        agent.open_submissions = 0;
+        agent.staked = 0;
+        agent.unstaking = 0;
+        agent.unstake_available_at = 0;
+
+        emit_cpi!(AgentRegistered {
+            agent: agent.owner,
+            profile: agent.key(),
+            name: agent.name.clone(),
+            bio: agent.bio.clone(),
+            skills: agent.skills.clone(),
+            hourly_rate: agent.hourly_rate,
+            registered_at: agent.registered_at,
+        });
+        Ok(())
+    }
+
+    /// Edit profile details (by agent)
+    pub fn update_agent(
+        ctx: Context<UpdateAgent>,
+        name: String,
+        bio: String,
+        skills: Vec<String>,
+        hourly_rate: u64,
+    ) -> Result<()> {
+        validate_agent_profile(&name, &bio, &skills)?;
+
+        let agent = &mut ctx.accounts.agent_profile;
+        agent.name = name;
+        agent.bio = bio;
+        agent.skills = skills;
+        agent.hourly_rate = hourly_rate;
+
+        emit_cpi!(AgentUpdated {
+            agent: agent.owner,
+            name: agent.name.clone(),
+            bio: agent.bio.clone(),
+            skills: agent.skills.clone(),
+            hourly_rate: agent.hourly_rate,
+        });
+        Ok(())
+    }
+
+    /// Pause or resume taking new work (by agent)
+    pub fn set_agent_active(ctx: Context<UpdateAgent>, is_active: bool) -> Result<()> {
+        let agent = &mut ctx.accounts.agent_profile;
+        agent.is_active = is_active;
+
+        emit_cpi!(AgentStatusChanged { agent: agent.owner, is_active });
+        Ok(())
+    }
+
+    /// Close the profile and reclaim rent, including any left in the stake vault (by agent, with no open submissions)
+    pub fn close_agent(ctx: Context<CloseAgent>) -> Result<()> {
+        let agent = &ctx.accounts.agent_profile;
+        let stake_vault = &ctx.accounts.stake_vault;
+        require!(agent.open_submissions == 0, OpenfourrError::HasOpenSubmissions);
+        require!(agent.staked == 0 && agent.unstaking == 0, OpenfourrError::HasStake);
+
+        // A slash that took the whole stake leaves the vault's rent behind
+        let remainder = stake_vault.lamports();
+        if remainder > 0 {
+            let owner_key = agent.owner;
+            let stake_seeds = &[b"stake".as_ref(), owner_key.as_ref(), &[ctx.bumps.stake_vault]];
+            let signer_seeds = &[&stake_seeds[..]];
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: stake_vault.to_account_info(),
+                    to: ctx.accounts.owner.to_account_info(),
+                },
+                signer_seeds,
+            );
+            anchor_lang::system_program::transfer(cpi_context, remainder)?;
+        }
+
+        emit_cpi!(AgentClosed { agent: agent.owner });
+        Ok(())
+    }
+
+    /// Deposit lamports into the agent's stake vault (by agent)
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        let agent = &mut ctx.accounts.agent_profile;
+        let stake_vault = &ctx.accounts.stake_vault;
+
+        require!(amount > 0, OpenfourrError::InvalidAmount);
+
+        // The first deposit also covers the vault's rent so partial slashes can't strand it
+        let deposit = if stake_vault.lamports() == 0 {
+            amount.checked_add(Rent::get()?.minimum_balance(0)).ok_or(OpenfourrError::MathOverflow)?
+        } else {
+            amount
+        };
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.owner.to_account_info(),
+                to: stake_vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, deposit)?;
+
+        agent.staked = agent.staked.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(Staked { agent: agent.owner, amount, total_staked: agent.staked });
+        Ok(())
+    }
+
+    /// Start the cooldown on part of the stake (by agent, with no open submissions or pending unstake)
+    pub fn request_unstake(ctx: Context<UpdateAgent>, amount: u64) -> Result<()> {
+        let agent = &mut ctx.accounts.agent_profile;
+
+        require!(amount > 0 && amount <= agent.staked, OpenfourrError::InvalidAmount);
+        require!(agent.open_submissions == 0, OpenfourrError::HasOpenSubmissions);
+        require!(agent.unstaking == 0, OpenfourrError::UnstakePending);
+
+        agent.staked = agent.staked.checked_sub(amount).ok_or(OpenfourrError::MathOverflow)?;
+        agent.unstaking = agent.unstaking.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+        agent.unstake_available_at = Clock::get()?.unix_timestamp.checked_add(UNSTAKE_COOLDOWN).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(UnstakeRequested { agent: agent.owner, amount, available_at: agent.unstake_available_at });
+        Ok(())
+    }
+
+    /// Withdraw stake whose cooldown has passed (by agent, with no open submissions)
+    pub fn withdraw_stake(ctx: Context<Stake>) -> Result<()> {
+        let agent = &mut ctx.accounts.agent_profile;
+        let stake_vault = &ctx.accounts.stake_vault;
+
+        require!(agent.unstaking > 0, OpenfourrError::NothingToUnstake);
+        require!(agent.open_submissions == 0, OpenfourrError::HasOpenSubmissions);
+        require!(
+            Clock::get()?.unix_timestamp >= agent.unstake_available_at,
+            OpenfourrError::UnstakeCooldownActive
+        );
+
+        // Fully unstaked agents also get the vault's rent back
+        let amount = if agent.staked == 0 { stake_vault.lamports() } else { agent.unstaking };
+
+        let owner_key = agent.owner;
+        let stake_seeds = &[b"stake".as_ref(), owner_key.as_ref(), &[ctx.bumps.stake_vault]];
+        let signer_seeds = &[&stake_seeds[..]];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: stake_vault.to_account_info(),
+                to: ctx.accounts.owner.to_account_info(),
+            },
+            signer_seeds,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let unstaked = agent.unstaking;
+        agent.unstaking = 0;
+
+        emit_cpi!(StakeWithdrawn { agent: agent.owner, amount: unstaked });
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_task(
         ctx: Context<CreateTask>,
         title: String,
@@ -53,48 +449,76 @@ pub mod openfourr {
         category: String,
         bounty_amount: u64,
         deadline_hours: u64,
+        options: TaskOptions,
     ) -> Result<()> {
-        require!(title.len() <= 100, OpenfourrError::TitleTooLong);
-        require!(description.len() <= 2000, OpenfourrError::DescriptionTooLong);
-        require!(bounty_amount > 0, OpenfourrError::InvalidBounty);
-        require!(deadline_hours > 0 && deadline_hours <= 720, OpenfourrError::InvalidDeadline);
+        let task_id = ctx.accounts.platform.total_tasks;
+        let created = NewTask {
+            task: &mut ctx.accounts.task,
+            client_profile: &mut ctx.accounts.client_profile,
+            platform: &ctx.accounts.platform,
+            escrow: &ctx.accounts.escrow,
+            client: &ctx.accounts.client,
+            system_program: &ctx.accounts.system_program,
+            mint: ctx.accounts.mint.as_ref(),
+            client_token_account: ctx.accounts.client_token_account.as_ref(),
+            escrow_token_account: ctx.accounts.escrow_token_account.as_ref(),
+            token_program: ctx.accounts.token_program.as_ref(),
+            associated_token_program: ctx.accounts.associated_token_program.as_ref(),
+            task_id,
+            per_client: false,
+            task_bump: ctx.bumps.task,
+            escrow_bump: ctx.bumps.escrow,
+            client_profile_bump: ctx.bumps.client_profile,
+        }
+        .open(title, description, requirements, category, bounty_amount, deadline_hours, options)?;
 
-        let task = &mut ctx.accounts.task;
         let platform = &mut ctx.accounts.platform;
+        platform.total_tasks = platform.total_tasks.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
 
-        // Transfer bounty to escrow
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.client.to_account_info(),
-                to: ctx.accounts.escrow.to_account_info(),
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_context, bounty_amount)?;
+        emit_cpi!(created);
+        Ok(())
+    }
 
-        task.id = platform.total_tasks;
-        task.client = ctx.accounts.client.key();
-        task.title = title.clone();
-        task.description = description;
-        task.requirements = requirements;
-        task.category = category;
-        task.bounty_amount = bounty_amount;
-        task.created_at = Clock::get()?.unix_timestamp;
-        task.deadline = Clock::get()?.unix_timestamp + (deadline_hours as i64 * 3600);
-        task.status = TaskStatus::Open;
-        task.submission_count = 0;
-        task.message_count = 0;
-        task.extension_requested = false;
-        task.extension_hours = 0;
-        task.dispute_raised_by = None;
-        task.winning_submission = None;
-        task.completed_at = None;
-        task.bump = ctx.bumps.task;
-        task.escrow_bump = ctx.bumps.escrow;
+    /// Post a task under the client's own nonce, without write-locking `Platform` (by client)
+    ///
+    /// Task ids are scoped to the client; `platform.total_tasks` only counts `create_task`,
+    /// so platform-wide task counts come from `TaskCreated` events.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_client_task(
+        ctx: Context<CreateClientTask>,
+        title: String,
+        description: String,
+        requirements: String,
+        category: String,
+        bounty_amount: u64,
+        deadline_hours: u64,
+        options: TaskOptions,
+    ) -> Result<()> {
+        let task_id = ctx.accounts.client_profile.task_nonce;
+        let created = NewTask {
+            task: &mut ctx.accounts.task,
+            client_profile: &mut ctx.accounts.client_profile,
+            platform: &ctx.accounts.platform,
+            escrow: &ctx.accounts.escrow,
+            client: &ctx.accounts.client,
+            system_program: &ctx.accounts.system_program,
+            mint: ctx.accounts.mint.as_ref(),
+            client_token_account: ctx.accounts.client_token_account.as_ref(),
+            escrow_token_account: ctx.accounts.escrow_token_account.as_ref(),
+            token_program: ctx.accounts.token_program.as_ref(),
+            associated_token_program: ctx.accounts.associated_token_program.as_ref(),
+            task_id,
+            per_client: true,
+            task_bump: ctx.bumps.task,
+            escrow_bump: ctx.bumps.escrow,
+            client_profile_bump: ctx.bumps.client_profile,
+        }
+        .open(title, description, requirements, category, bounty_amount, deadline_hours, options)?;
 
-        platform.total_tasks += 1;
+        let client_profile = &mut ctx.accounts.client_profile;
+        client_profile.task_nonce = client_profile.task_nonce.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
 
-        emit!(TaskCreated { task_id: task.id, client: task.client, title, bounty: bounty_amount, deadline: task.deadline });
+        emit_cpi!(created);
         Ok(())
     }
 
@@ -104,23 +528,123 @@ pub mod openfourr {
         submission_notes: String,
     ) -> Result<()> {
         let task = &mut ctx.accounts.task;
-        let agent = &ctx.accounts.agent_profile;
+        let agent = &mut ctx.accounts.agent_profile;
         let submission = &mut ctx.accounts.submission;
 
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
         require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode == TaskMode::OpenCompetition, OpenfourrError::WrongTaskMode);
+        require!(!task.commit_reveal, OpenfourrError::CommitRequired);
         require!(agent.is_active, OpenfourrError::AgentNotActive);
+        require!(task.is_invited(&agent.owner), OpenfourrError::NotInvited);
+        require!(agent.staked >= task.min_stake, OpenfourrError::InsufficientStake);
         require!(Clock::get()?.unix_timestamp < task.deadline, OpenfourrError::TaskExpired);
-        require!(submission_url.len() <= 500, OpenfourrError::UrlTooLong);
+        validate_submission(&submission_url, &submission_notes)?;
 
         submission.task_id = task.id;
+        submission.task = task.key();
         submission.agent = agent.owner;
         submission.submission_url = submission_url;
         submission.submission_notes = submission_notes;
         submission.submitted_at = Clock::get()?.unix_timestamp;
         submission.status = SubmissionStatus::Pending;
         submission.bump = ctx.bumps.submission;
+        submission.commitment = None;
+        submission.revealed_at = None;
+        submission.funder_votes = 0;
+        submission.client_rating = None;
+
+        task.submission_count = task.submission_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        agent.open_submissions = agent.open_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(SubmissionCreated {
+            task: task.key(),
+            task_id: task.id,
+            submission: submission.key(),
+            agent: agent.owner,
+            submission_url: submission.submission_url.clone(),
+            submission_notes: submission.submission_notes.clone(),
+            submitted_at: submission.submitted_at,
+        });
+        Ok(())
+    }
+
+    /// Commit to a sealed submission on a commit-reveal task (by agent)
+    pub fn commit_submission(ctx: Context<SubmitApplication>, commitment: [u8; 32]) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let agent = &mut ctx.accounts.agent_profile;
+        let submission = &mut ctx.accounts.submission;
+
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.commit_reveal, OpenfourrError::NotCommitReveal);
+        require!(agent.is_active, OpenfourrError::AgentNotActive);
+        require!(task.is_invited(&agent.owner), OpenfourrError::NotInvited);
+        require!(agent.staked >= task.min_stake, OpenfourrError::InsufficientStake);
+        require!(Clock::get()?.unix_timestamp < task.deadline, OpenfourrError::TaskExpired);
+
+        submission.task_id = task.id;
+        submission.task = task.key();
+        submission.agent = agent.owner;
+        submission.submission_url = String::new();
+        submission.submission_notes = String::new();
+        submission.submitted_at = Clock::get()?.unix_timestamp;
+        submission.status = SubmissionStatus::Pending;
+        submission.bump = ctx.bumps.submission;
+        submission.commitment = Some(commitment);
+        submission.revealed_at = None;
+        submission.funder_votes = 0;
+        submission.client_rating = None;
+
+        task.submission_count = task.submission_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        agent.open_submissions = agent.open_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(SubmissionCommitted {
+            task: task.key(),
+            task_id: task.id,
+            submission: submission.key(),
+            agent: agent.owner,
+            commitment,
+            committed_at: submission.submitted_at,
+        });
+        Ok(())
+    }
+
+    /// Reveal a sealed submission after the deadline (by agent)
+    pub fn reveal_submission(
+        ctx: Context<RevealSubmission>,
+        submission_url: String,
+        submission_notes: String,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let submission = &mut ctx.accounts.submission;
+        let now = Clock::get()?.unix_timestamp;
+
+        let commitment = submission.commitment.ok_or(OpenfourrError::NotCommitReveal)?;
+        require!(submission.revealed_at.is_none(), OpenfourrError::AlreadyRevealed);
+        require!(now >= task.deadline, OpenfourrError::RevealNotStarted);
+        require!(!task.reveal_window_closed(now)?, OpenfourrError::RevealWindowClosed);
+        validate_submission(&submission_url, &submission_notes)?;
+        require!(
+            submission_commitment(&submission.agent, &submission_url, &submission_notes, &salt) == commitment,
+            OpenfourrError::CommitmentMismatch
+        );
+
+        submission.submission_url = submission_url;
+        submission.submission_notes = submission_notes;
+        submission.revealed_at = Some(now);
+        task.revealed_count = task.revealed_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
 
-        task.submission_count += 1;
+        emit_cpi!(SubmissionRevealed {
+            task: task.key(),
+            task_id: task.id,
+            submission: submission.key(),
+            agent: submission.agent,
+            submission_url: submission.submission_url.clone(),
+            submission_notes: submission.submission_notes.clone(),
+            revealed_at: now,
+        });
         Ok(())
     }
 
@@ -129,105 +653,340 @@ pub mod openfourr {
         let message = &mut ctx.accounts.message;
         let sender = ctx.accounts.sender.key();
 
-        require!(content.len() <= 500, OpenfourrError::MessageTooLong);
-        require!(content.len() > 0, OpenfourrError::MessageEmpty);
+        validate_message(&content)?;
 
         let is_client = task.client == sender;
         let is_agent = ctx.accounts.submission.is_some();
         require!(is_client || is_agent, OpenfourrError::NotTaskParticipant);
 
         message.task_id = task.id;
+        message.task = task.key();
         message.message_id = task.message_count;
         message.sender = sender;
         message.content = content;
         message.sent_at = Clock::get()?.unix_timestamp;
         message.bump = ctx.bumps.message;
 
-        task.message_count += 1;
+        task.message_count = task.message_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(MessageSent {
+            task: task.key(),
+            task_id: task.id,
+            message: message.key(),
+            message_id: message.message_id,
+            sender,
+            content: message.content.clone(),
+            sent_at: message.sent_at,
+        });
         Ok(())
     }
 
-    pub fn select_winner(ctx: Context<SelectWinner>, rating: u8) -> Result<()> {
-        require!(rating >= 1 && rating <= 5, OpenfourrError::InvalidRating);
+    /// Split an exclusive-claim task's bounty into milestones (by client, before any claim)
+    pub fn add_milestones(ctx: Context<AddMilestones>, milestones: Vec<MilestoneInput>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let plan = &mut ctx.accounts.milestone_plan;
+
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode == TaskMode::ExclusiveClaim, OpenfourrError::WrongTaskMode);
+        require!(task.submission_count == 0, OpenfourrError::HasSubmissions);
+        require!(
+            (MIN_MILESTONES..=MAX_MILESTONES).contains(&milestones.len()),
+            OpenfourrError::InvalidMilestoneCount
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut total = 0u64;
+        let mut previous_deadline = now;
+        for input in &milestones {
+            validate_milestone_title(&input.title)?;
+            require!(input.amount > 0, OpenfourrError::InvalidBounty);
 
+            let deadline = hours_after(now, input.deadline_hours)?;
+            require!(
+                deadline > previous_deadline && deadline <= task.deadline,
+                OpenfourrError::InvalidDeadline
+            );
+
+            previous_deadline = deadline;
+            total = total.checked_add(input.amount).ok_or(OpenfourrError::MathOverflow)?;
+        }
+        require!(total == task.bounty_amount, OpenfourrError::MilestoneSumMismatch);
+
+        plan.task = task.key();
+        plan.milestones = milestones
+            .into_iter()
+            .map(|input| {
+                Ok(Milestone {
+                    title: input.title,
+                    amount: input.amount,
+                    deadline: hours_after(now, input.deadline_hours)?,
+                    released_at: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        plan.released_count = 0;
+        plan.bump = ctx.bumps.milestone_plan;
+
+        task.milestone_count = plan.milestones.len() as u8;
+
+        emit_cpi!(MilestonesAdded {
+            task: task.key(),
+            task_id: task.id,
+            milestone_plan: plan.key(),
+            milestones: plan.milestones.clone(),
+            total,
+        });
+        Ok(())
+    }
+
+    /// Release the next milestone to the assigned agent (by client or task judge)
+    pub fn release_milestone(ctx: Context<ReleaseMilestone>, index: u8, rating: Option<u8>) -> Result<()> {
         let task = &mut ctx.accounts.task;
+        let plan = &mut ctx.accounts.milestone_plan;
         let submission = &mut ctx.accounts.submission;
         let agent = &mut ctx.accounts.agent_profile;
         let platform = &mut ctx.accounts.platform;
+        let releaser = ctx.accounts.releaser.key();
 
-        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
-        require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+        require!(!platform.paused, OpenfourrError::PlatformPaused);
+        require!(
+            releaser == task.client || task.judge == Some(releaser),
+            OpenfourrError::NotMilestoneReleaser
+        );
+        require!(
+            matches!(task.status, TaskStatus::InProgress | TaskStatus::PendingReview),
+            OpenfourrError::TaskNotInProgress
+        );
+        require!(task.assigned_agent == Some(submission.agent), OpenfourrError::NotAssignedAgent);
+        require!(index == plan.released_count, OpenfourrError::MilestoneOutOfOrder);
+        if let Some(rating) = rating {
+            require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+        }
 
-        let fee = task.bounty_amount * (platform.fee_bps as u64) / 10000;
-        let payout = task.bounty_amount - fee;
+        let milestone = plan.milestones.get_mut(index as usize).ok_or(OpenfourrError::InvalidMilestone)?;
+        let amount = milestone.amount;
 
-        let task_id_bytes = task.id.to_le_bytes();
-        let escrow_seeds = &[b"escrow".as_ref(), task_id_bytes.as_ref(), &[task.escrow_bump]];
-        let signer_seeds = &[&escrow_seeds[..]];
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.releaser,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+        let (payout, _) = vault.pay_agent(
+            platform,
+            amount,
+            &ctx.accounts.agent_wallet,
+            ctx.accounts.agent_token_account.as_deref(),
+            &ctx.accounts.platform_treasury,
+            ctx.accounts.treasury_token_account.as_deref(),
+        )?;
 
-        // Pay agent
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.escrow.to_account_info(),
-                to: ctx.accounts.agent_wallet.to_account_info(),
-            },
-            signer_seeds,
-        );
-        anchor_lang::system_program::transfer(cpi_context, payout)?;
+        let released_at = Clock::get()?.unix_timestamp;
+        milestone.released_at = Some(released_at);
+        plan.released_count = plan.released_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        task.released_amount = task.released_amount.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
 
-        // Pay platform fee
-        if fee > 0 {
-            let fee_context = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.escrow.to_account_info(),
-                    to: ctx.accounts.platform_treasury.to_account_info(),
-                },
-                signer_seeds,
-            );
-            anchor_lang::system_program::transfer(fee_context, fee)?;
+        if plan.released_count as usize == plan.milestones.len() {
+            vault.close(&ctx.accounts.client, ctx.accounts.client_token_account.as_deref())?;
+            record_win(task, submission, agent, platform, &mut ctx.accounts.client_profile, amount, payout, rating)?;
+        } else {
+            task.status = TaskStatus::InProgress;
+            if task.mint.is_none() {
+                agent.total_earned = agent.total_earned.checked_add(payout).ok_or(OpenfourrError::MathOverflow)?;
+                platform.total_volume = platform.total_volume.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+            }
+            credit_client(task, &mut ctx.accounts.client_profile, amount)?;
         }
 
-        submission.status = SubmissionStatus::Selected;
-        task.status = TaskStatus::Completed;
-        task.winning_submission = Some(ctx.accounts.submission.key());
-        task.completed_at = Some(Clock::get()?.unix_timestamp);
+        emit_cpi!(MilestoneReleased {
+            task: task.key(),
+            task_id: task.id,
+            index,
+            agent: submission.agent,
+            amount,
+            payout,
+            released_at,
+        });
+        Ok(())
+    }
 
-        agent.tasks_completed += 1;
-        agent.total_earned += payout;
-        agent.rating_sum += rating as u64;
-        agent.rating_count += 1;
+    pub fn select_winner(ctx: Context<SelectWinner>, rating: u8) -> Result<()> {
+        let task = &ctx.accounts.task;
 
-        platform.total_completed += 1;
-        platform.total_volume += task.bounty_amount;
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode == TaskMode::OpenCompetition, OpenfourrError::WrongTaskMode);
+        require!(task.payout_schedule.is_empty(), OpenfourrError::MultipleWinnersRequired);
 
-        emit!(WinnerSelected { task_id: task.id, agent: agent.owner, payout, rating });
+        let selected = ctx.accounts.pay_winner(rating)?;
+        emit_cpi!(selected);
         Ok(())
     }
 
-    pub fn cancel_task(ctx: Context<CancelTask>) -> Result<()> {
+    /// Pay several submissions by the task's payout schedule, best place first (by client)
+    ///
+    /// `remaining_accounts` holds `[submission, agent_profile, agent_wallet]` per place, plus the
+    /// agent's token account on SPL tasks; `ratings` lines up with the places. Fewer winners than
+    /// places is allowed; the shares of the unfilled places are refunded like a cancellation.
+    pub fn select_winners<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SelectWinners<'info>>,
+        ratings: Vec<u8>,
+    ) -> Result<()> {
         let task = &mut ctx.accounts.task;
+        let platform = &mut ctx.accounts.platform;
+        let task_key = task.key();
 
-        require!(task.status == TaskStatus::Open, OpenfourrError::CannotCancel);
-        require!(task.submission_count == 0, OpenfourrError::HasSubmissions);
+        require!(!platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(!task.payout_schedule.is_empty(), OpenfourrError::SingleWinnerTask);
+        require!(task.verdict.is_none(), OpenfourrError::VerdictPending);
+        require_reveals_settled(task)?;
+        require!(
+            !ratings.is_empty() && ratings.len() <= task.payout_schedule.len(),
+            OpenfourrError::InvalidRemainingAccounts
+        );
 
-        let task_id_bytes = task.id.to_le_bytes();
-        let escrow_seeds = &[b"escrow".as_ref(), task_id_bytes.as_ref(), &[task.escrow_bump]];
-        let signer_seeds = &[&escrow_seeds[..]];
+        let group = if task.mint.is_some() { 4 } else { 3 };
+        let groups = account_groups(ctx.remaining_accounts, group)?;
+        require!(groups.len() == ratings.len(), OpenfourrError::InvalidRemainingAccounts);
 
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.escrow.to_account_info(),
-                to: ctx.accounts.client.to_account_info(),
-            },
-            signer_seeds,
-        );
-        anchor_lang::system_program::transfer(cpi_context, task.bounty_amount)?;
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.client,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
 
-        task.status = TaskStatus::Cancelled;
-        emit!(TaskCancelled { task_id: task.id });
+        let bounty = task.bounty_amount;
+        let schedule = task.payout_schedule.clone();
+        let mut winners: Vec<Pubkey> = Vec::with_capacity(schedule.len());
+        let mut paid = 0u64;
+
+        for (place, (accounts, (&bps, &rating))) in groups.zip(schedule.iter().zip(ratings.iter())).enumerate() {
+            require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+
+            let mut submission = Account::<Submission>::try_from(&accounts[0])?;
+            let mut agent = Account::<AgentProfile>::try_from(&accounts[1])?;
+            let agent_wallet = &accounts[2];
+            let agent_token_account = task.mint.map(|_| &accounts[3]);
+
+            let expected = Pubkey::create_program_address(
+                &[b"submission", task_key.as_ref(), submission.agent.as_ref(), &[submission.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| OpenfourrError::InvalidRemainingAccounts)?;
+            require_keys_eq!(submission.key(), expected, OpenfourrError::InvalidRemainingAccounts);
+            require!(!winners.contains(&submission.key()), OpenfourrError::DuplicateWinner);
+            require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+            require!(submission.is_revealed(), OpenfourrError::SubmissionNotRevealed);
+            require!(
+                agent.owner == submission.agent && agent_wallet.key() == submission.agent,
+                OpenfourrError::AgentMismatch
+            );
+            if place == 0 {
+                if let Some(leader) = task.binding_vote() {
+                    require_keys_eq!(submission.key(), leader, OpenfourrError::OverruledByFunders);
+                }
+            }
+
+            // The last place of a full schedule takes the rounding remainder so the escrow ends empty
+            let gross = if place + 1 == schedule.len() {
+                bounty.checked_sub(paid).ok_or(OpenfourrError::MathOverflow)?
+            } else {
+                bps_of(bounty, bps)?
+            };
+            paid = paid.checked_add(gross).ok_or(OpenfourrError::MathOverflow)?;
+
+            let (payout, _) = vault.pay_agent(
+                platform,
+                gross,
+                agent_wallet,
+                agent_token_account,
+                &ctx.accounts.platform_treasury,
+                ctx.accounts.treasury_token_account.as_deref(),
+            )?;
+            credit_winner(task, &mut submission, &mut agent, platform, gross, payout, Some(rating))?;
+
+            submission.exit(&crate::ID)?;
+            agent.exit(&crate::ID)?;
+            winners.push(submission.key());
+
+            emit_cpi!(WinnerSelected {
+                task: task.key(),
+                task_id: task.id,
+                submission: submission.key(),
+                agent: agent.owner,
+                payout,
+                rating,
+            });
+        }
+
+        // Places left unfilled go back to the funders
+        let refund = bounty.checked_sub(paid).ok_or(OpenfourrError::MathOverflow)?;
+        vault.refund(task, &ctx.accounts.client, ctx.accounts.client_token_account.as_deref(), refund)?;
+        if refund > 0 {
+            emit_cpi!(UnfilledPlacesRefunded {
+                task: task.key(),
+                task_id: task.id,
+                client: ctx.accounts.client.key(),
+                refund,
+                refund_pool: task.refund_pool,
+            });
+        }
+
+        task.status = TaskStatus::Completed;
+        task.winning_submission = winners.first().copied();
+        task.completed_at = Some(Clock::get()?.unix_timestamp);
+        platform.total_completed = platform.total_completed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        record_client_completion(task, &mut ctx.accounts.client_profile, paid)?;
+        Ok(())
+    }
+
+    pub fn cancel_task(ctx: Context<CancelTask>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+
+        if task.milestone_count > 0 {
+            // Milestone tasks can be cancelled mid-way once the agent misses the next milestone's deadline;
+            // released milestones stay with the agent
+            match task.status {
+                TaskStatus::Open | TaskStatus::Rejected => {}
+                TaskStatus::InProgress => {
+                    let plan = ctx.accounts.milestone_plan.as_ref().ok_or(OpenfourrError::MilestonePlanRequired)?;
+                    let next = plan.milestones.get(plan.released_count as usize).ok_or(OpenfourrError::InvalidMilestone)?;
+                    require!(Clock::get()?.unix_timestamp > next.deadline, OpenfourrError::MilestoneNotOverdue);
+                }
+                _ => return err!(OpenfourrError::CannotCancel),
+            }
+        } else if task.status == TaskStatus::Open {
+            // Sealed submissions nobody revealed in time are forfeited and don't hold the task open
+            let all_forfeited = task.commit_reveal && task.revealed_count == 0 && task.reveal_window_closed(Clock::get()?.unix_timestamp)?;
+            require!(task.submission_count == 0 || all_forfeited, OpenfourrError::HasSubmissions);
+        } else {
+            require!(task.status == TaskStatus::Rejected, OpenfourrError::CannotCancel);
+        }
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.client,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+        let refund = task.remaining_bounty()?;
+        vault.refund(task, &ctx.accounts.client, ctx.accounts.client_token_account.as_deref(), refund)?;
+
+        record_cancel(task, &mut ctx.accounts.client_profile)?;
+        emit_cpi!(TaskCancelled { task: task.key(), task_id: task.id, refund });
         Ok(())
     }
 
@@ -235,14 +994,17 @@ pub mod openfourr {
     pub fn request_extension(ctx: Context<RequestExtension>, extra_hours: u64) -> Result<()> {
         let task = &mut ctx.accounts.task;
         
-        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(
+            matches!(task.status, TaskStatus::Open | TaskStatus::InProgress),
+            OpenfourrError::TaskNotOpen
+        );
         require!(extra_hours > 0 && extra_hours <= 168, OpenfourrError::InvalidExtension);
         require!(!task.extension_requested, OpenfourrError::ExtensionAlreadyRequested);
 
         task.extension_requested = true;
         task.extension_hours = extra_hours;
 
-        emit!(ExtensionRequested { task_id: task.id, agent: ctx.accounts.agent_owner.key(), extra_hours });
+        emit_cpi!(ExtensionRequested { task: task.key(), task_id: task.id, agent: ctx.accounts.agent_owner.key(), extra_hours });
         Ok(())
     }
 
@@ -252,11 +1014,11 @@ pub mod openfourr {
 
         require!(task.extension_requested, OpenfourrError::NoExtensionRequested);
 
-        task.deadline += (task.extension_hours as i64) * 3600;
+        task.deadline = hours_after(task.deadline, task.extension_hours)?;
         task.extension_requested = false;
         task.extension_hours = 0;
 
-        emit!(ExtensionApproved { task_id: task.id, new_deadline: task.deadline });
+        emit_cpi!(ExtensionApproved { task: task.key(), task_id: task.id, new_deadline: task.deadline });
         Ok(())
     }
 
@@ -268,21 +1030,290 @@ pub mod openfourr {
         task.extension_requested = false;
         task.extension_hours = 0;
 
-        emit!(ExtensionDenied { task_id: task.id });
+        emit_cpi!(ExtensionDenied { task: task.key(), task_id: task.id });
         Ok(())
     }
 
-    /// Raise dispute
-    pub fn raise_dispute(ctx: Context<RaiseDispute>) -> Result<()> {
+    /// Edit task details and reset the deadline (by client, before any submission)
+    pub fn update_task(
+        ctx: Context<UpdateTask>,
+        title: String,
+        description: String,
+        requirements: String,
+        category: String,
+        deadline_hours: u64,
+    ) -> Result<()> {
+        validate_task_details(&title, &description, &requirements, &category, deadline_hours)?;
+
+        let task = &mut ctx.accounts.task;
+
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.submission_count == 0, OpenfourrError::HasSubmissions);
+        require!(task.assigned_agent.is_none(), OpenfourrError::BidAlreadyAccepted);
+
+        let deadline = hours_after(Clock::get()?.unix_timestamp, deadline_hours)?;
+        if task.milestone_count > 0 {
+            let plan = ctx.accounts.milestone_plan.as_ref().ok_or(OpenfourrError::MilestonePlanRequired)?;
+            let last = plan.milestones.last().ok_or(OpenfourrError::InvalidMilestone)?;
+            require!(deadline >= last.deadline, OpenfourrError::InvalidDeadline);
+        }
+
+        task.title = title;
+        task.description = description;
+        task.requirements = requirements;
+        task.category = category;
+        task.deadline = deadline;
+
+        emit_cpi!(TaskUpdated {
+            task: task.key(),
+            task_id: task.id,
+            title: task.title.clone(),
+            description: task.description.clone(),
+            requirements: task.requirements.clone(),
+            category: task.category.clone(),
+            deadline: task.deadline,
+        });
+        Ok(())
+    }
+
+    /// Add funds to an open task's escrow (by client)
+    pub fn top_up_bounty(ctx: Context<TopUpBounty>, amount: u64) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(amount > 0, OpenfourrError::InvalidBounty);
+        // Milestone amounts must keep summing to the bounty
+        require!(task.milestone_count == 0, OpenfourrError::HasMilestones);
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.client,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            None,
+        )?;
+        vault.deposit(&ctx.accounts.client, ctx.accounts.client_token_account.as_ref(), amount)?;
+
+        task.bounty_amount = task.bounty_amount.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(BountyIncreased { task: task.key(), task_id: task.id, amount, new_bounty: task.bounty_amount });
+        Ok(())
+    }
+
+    /// Add funds to an open crowdfunded task (by any wallet other than the client)
+    pub fn contribute(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let contribution = &mut ctx.accounts.contribution;
+        let funder = ctx.accounts.funder.key();
+
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.crowdfunded, OpenfourrError::NotCrowdfunded);
+        require!(funder != task.client, OpenfourrError::ClientCannotContribute);
+        require!(contribution.vote.is_none(), OpenfourrError::ContributionLocked);
+        require!(amount > 0, OpenfourrError::InvalidBounty);
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.funder,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            None,
+        )?;
+        vault.deposit(&ctx.accounts.funder, ctx.accounts.funder_token_account.as_ref(), amount)?;
+
+        if contribution.amount == 0 {
+            contribution.task = task.key();
+            contribution.funder = funder;
+            contribution.vote = None;
+            contribution.bump = ctx.bumps.contribution;
+            task.contributor_count = task.contributor_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        }
+        contribution.amount = contribution.amount.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+        task.contributed = task.contributed.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+        task.bounty_amount = task.bounty_amount.checked_add(amount).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(ContributionAdded {
+            task: task.key(),
+            task_id: task.id,
+            contribution: contribution.key(),
+            funder,
+            amount,
+            total_contributed: contribution.amount,
+            new_bounty: task.bounty_amount,
+        });
+        Ok(())
+    }
+
+    /// Back a submission as winner, weighted by the amount contributed (by funder, once)
+    pub fn vote_winner(ctx: Context<VoteWinner>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let contribution = &mut ctx.accounts.contribution;
+        let submission = &mut ctx.accounts.submission;
+
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(contribution.vote.is_none(), OpenfourrError::AlreadyVoted);
+        require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+
+        contribution.vote = Some(submission.key());
+        submission.funder_votes = submission.funder_votes.checked_add(contribution.amount).ok_or(OpenfourrError::MathOverflow)?;
+
+        // A majority of the contributed funds overrides the client's choice of winner
+        if (submission.funder_votes as u128) * 2 > task.contributed as u128 {
+            task.vote_leader = Some(submission.key());
+            task.vote_leader_votes = submission.funder_votes;
+        }
+
+        emit_cpi!(FunderVoted {
+            task: task.key(),
+            task_id: task.id,
+            funder: contribution.funder,
+            submission: submission.key(),
+            weight: contribution.amount,
+            total_votes: submission.funder_votes,
+            vote_leader: task.vote_leader,
+        });
+        Ok(())
+    }
+
+    /// Pay a contributor's share of a refunded task and close the contribution to its funder (permissionless)
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let contribution = &ctx.accounts.contribution;
+
+        require!(task.is_terminal(), OpenfourrError::TaskNotFinished);
+
+        let last_claim = task.refunds_claimed.checked_add(1).ok_or(OpenfourrError::MathOverflow)? == task.contributor_count;
+        let refund = if task.refund_pool == 0 {
+            0
+        } else if last_claim {
+            // The last contributor sweeps rounding dust so the escrow ends empty
+            task.refund_pool.checked_sub(task.refunds_paid).ok_or(OpenfourrError::MathOverflow)?
+        } else {
+            mul_div(task.refund_pool, contribution.amount, task.contributed)?
+        };
+
+        if task.refund_pool > 0 {
+            let vault = EscrowVault::new(
+                task,
+                &ctx.accounts.escrow,
+                &ctx.accounts.system_program,
+                &ctx.accounts.payer,
+                ctx.accounts.mint.as_ref(),
+                ctx.accounts.escrow_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                ctx.accounts.associated_token_program.as_ref(),
+            )?;
+            vault.pay(&ctx.accounts.funder, ctx.accounts.funder_token_account.as_deref(), refund)?;
+            if last_claim {
+                vault.close(&ctx.accounts.client, ctx.accounts.client_token_account.as_deref())?;
+            }
+        }
+
+        task.refunds_paid = task.refunds_paid.checked_add(refund).ok_or(OpenfourrError::MathOverflow)?;
+        task.refunds_claimed = task.refunds_claimed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(RefundClaimed { task: task.key(), task_id: task.id, funder: contribution.funder, amount: refund });
+        Ok(())
+    }
+
+    /// Replace the invite list of an open task; an empty list opens it to every agent (by client)
+    pub fn set_invited_agents(ctx: Context<UpdateTask>, invited_agents: Vec<Pubkey>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        validate_invites(&invited_agents)?;
+
+        task.invite_only = !invited_agents.is_empty();
+        task.invited_agents = invited_agents;
+
+        emit_cpi!(InvitesUpdated { task: task.key(), task_id: task.id, invited_agents: task.invited_agents.clone() });
+        Ok(())
+    }
+
+    /// Turn down an invitation to an invite-only task (by invited agent)
+    pub fn decline_invite(ctx: Context<DeclineInvite>) -> Result<()> {
         let task = &mut ctx.accounts.task;
+        let agent = ctx.accounts.agent_owner.key();
 
         require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        let index = task.invited_agents.iter().position(|invited| *invited == agent).ok_or(OpenfourrError::NotInvited)?;
+        // The task stays invite-only, so the client has to re-open it or cancel once everyone declines
+        task.invited_agents.remove(index);
+
+        emit_cpi!(InviteDeclined { task: task.key(), task_id: task.id, agent, remaining: task.invited_agents.len() as u8 });
+        Ok(())
+    }
+
+    /// Raise dispute
+    pub fn raise_dispute(ctx: Context<RaiseDispute>, reason: String, evidence_uri: String) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let dispute = &mut ctx.accounts.dispute;
+        let raiser = ctx.accounts.raiser.key();
+        let bond = ctx.accounts.platform.dispute_bond;
+
+        require!(
+            matches!(task.status, TaskStatus::Open | TaskStatus::InProgress | TaskStatus::PendingReview),
+            OpenfourrError::TaskNotOpen
+        );
         require!(task.submission_count > 0, OpenfourrError::NoSubmissions);
+        if let Some(verdict) = task.verdict {
+            require!(
+                Clock::get()?.unix_timestamp < verdict.issued_at.checked_add(JUDGE_CHALLENGE_WINDOW).ok_or(OpenfourrError::MathOverflow)?,
+                OpenfourrError::ChallengeWindowClosed
+            );
+        }
+        validate_reason(&reason)?;
+        validate_evidence_uri(&evidence_uri)?;
+
+        // Only the client or an agent who submitted to this task may dispute it
+        if raiser != task.client {
+            let submission = ctx.accounts.submission.as_ref().ok_or(OpenfourrError::NotParticipant)?;
+            require!(
+                submission.task == task.key() && submission.agent == raiser,
+                OpenfourrError::NotParticipant
+            );
+        }
+
+        if bond > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.raiser.to_account_info(),
+                    to: dispute.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, bond)?;
+        }
+
+        dispute.task = task.key();
+        dispute.raised_by = raiser;
+        dispute.reason = reason;
+        dispute.evidence_uri = evidence_uri;
+        dispute.bond = bond;
+        dispute.raised_at = Clock::get()?.unix_timestamp;
+        dispute.bump = ctx.bumps.dispute;
 
         task.status = TaskStatus::Disputed;
-        task.dispute_raised_by = Some(ctx.accounts.raiser.key());
+        task.dispute_raised_by = Some(raiser);
 
-        emit!(DisputeRaised { task_id: task.id, raised_by: ctx.accounts.raiser.key() });
+        emit_cpi!(DisputeRaised {
+            task: task.key(),
+            task_id: task.id,
+            dispute: dispute.key(),
+            raised_by: raiser,
+            reason: dispute.reason.clone(),
+            evidence_uri: dispute.evidence_uri.clone(),
+            bond,
+            raised_at: dispute.raised_at,
+        });
         Ok(())
     }
 
@@ -290,168 +1321,2948 @@ pub mod openfourr {
     pub fn auto_refund_expired(ctx: Context<AutoRefundExpired>) -> Result<()> {
         let task = &mut ctx.accounts.task;
 
-        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(
+            matches!(task.status, TaskStatus::Open | TaskStatus::InProgress),
+            OpenfourrError::TaskNotOpen
+        );
+        require!(task.verdict.is_none(), OpenfourrError::VerdictPending);
         
         let grace_period = 7 * 24 * 3600;
         let current_time = Clock::get()?.unix_timestamp;
-        require!(current_time > task.deadline + grace_period, OpenfourrError::GracePeriodNotOver);
+        let refundable_at = task.deadline.checked_add(grace_period).ok_or(OpenfourrError::MathOverflow)?;
+        require!(current_time > refundable_at, OpenfourrError::GracePeriodNotOver);
 
-        let task_id_bytes = task.id.to_le_bytes();
-        let escrow_seeds = &[b"escrow".as_ref(), task_id_bytes.as_ref(), &[task.escrow_bump]];
-        let signer_seeds = &[&escrow_seeds[..]];
-
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.escrow.to_account_info(),
-                to: ctx.accounts.client.to_account_info(),
-            },
-            signer_seeds,
-        );
-        anchor_lang::system_program::transfer(cpi_context, task.bounty_amount)?;
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payer,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+        let refund = task.remaining_bounty()?;
+        vault.refund(task, &ctx.accounts.client, ctx.accounts.client_token_account.as_deref(), refund)?;
 
         task.status = TaskStatus::Cancelled;
-        emit!(AutoRefunded { task_id: task.id });
+        task.completed_at = Some(current_time);
+        let client_profile = &mut ctx.accounts.client_profile;
+        client_profile.adopt(task.client);
+        client_profile.tasks_expired = client_profile.tasks_expired.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        emit_cpi!(AutoRefunded { task: task.key(), task_id: task.id, refund });
         Ok(())
     }
-}
 
-// ============ CONTEXTS ============
+    /// Close a submission on a finished task, refunding rent to the agent (permissionless)
+    pub fn close_submission(ctx: Context<CloseSubmission>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let submission = &ctx.accounts.submission;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = authority, space = 8 + Platform::INIT_SPACE, seeds = [b"platform"], bump)]
-    pub platform: Account<'info, Platform>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        require_closable(task, &ctx.accounts.platform)?;
 
-#[derive(Accounts)]
-pub struct RegisterAgent<'info> {
-    #[account(init, payer = owner, space = 8 + AgentProfile::INIT_SPACE, seeds = [b"agent", owner.key().as_ref()], bump)]
-    pub agent_profile: Account<'info, AgentProfile>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        require_rating_window_closed(task, submission)?;
 
-#[derive(Accounts)]
-pub struct CreateTask<'info> {
-    #[account(init, payer = client, space = 8 + Task::INIT_SPACE, seeds = [b"task", platform.total_tasks.to_le_bytes().as_ref()], bump)]
-    pub task: Account<'info, Task>,
-    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
-    pub platform: Account<'info, Platform>,
-    /// CHECK: Escrow PDA
-    #[account(mut, seeds = [b"escrow", platform.total_tasks.to_le_bytes().as_ref()], bump)]
-    pub escrow: AccountInfo<'info>,
-    #[account(mut)]
-    pub client: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        // Submissions never picked or rejected still count as open work for the agent
+        if submission.status == SubmissionStatus::Pending {
+            let agent = ctx.accounts.agent_profile.as_mut().ok_or(OpenfourrError::AgentProfileRequired)?;
+            require_keys_eq!(agent.owner, submission.agent, OpenfourrError::AgentMismatch);
+            agent.open_submissions = agent.open_submissions.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+        }
 
-#[derive(Accounts)]
-pub struct SubmitApplication<'info> {
-    #[account(init, payer = agent_owner, space = 8 + Submission::INIT_SPACE, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump)]
-    pub submission: Account<'info, Submission>,
-    #[account(mut)]
-    pub task: Account<'info, Task>,
-    #[account(seeds = [b"agent", agent_owner.key().as_ref()], bump = agent_profile.bump)]
-    pub agent_profile: Account<'info, AgentProfile>,
-    #[account(mut)]
-    pub agent_owner: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        task.closed_submissions = task.closed_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
 
-#[derive(Accounts)]
-pub struct SendMessage<'info> {
-    #[account(init, payer = sender, space = 8 + Message::INIT_SPACE, seeds = [b"message", task.key().as_ref(), task.message_count.to_le_bytes().as_ref()], bump)]
-    pub message: Account<'info, Message>,
-    #[account(mut)]
-    pub task: Account<'info, Task>,
-    pub submission: Option<Account<'info, Submission>>,
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        emit_cpi!(SubmissionClosed { task: task.key(), task_id: task.id, submission: submission.key(), agent: submission.agent });
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct SelectWinner<'info> {
-    #[account(mut, constraint = task.client == client.key())]
-    pub task: Account<'info, Task>,
-    #[account(mut, constraint = submission.task_id == task.id)]
-    pub submission: Account<'info, Submission>,
-    #[account(mut, seeds = [b"agent", submission.agent.as_ref()], bump = agent_profile.bump)]
-    pub agent_profile: Account<'info, AgentProfile>,
-    /// CHECK: Agent wallet
-    #[account(mut, constraint = agent_wallet.key() == submission.agent)]
-    pub agent_wallet: AccountInfo<'info>,
-    /// CHECK: Escrow
-    #[account(mut, seeds = [b"escrow", task.id.to_le_bytes().as_ref()], bump = task.escrow_bump)]
-    pub escrow: AccountInfo<'info>,
-    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
-    pub platform: Account<'info, Platform>,
-    /// CHECK: Treasury
-    #[account(mut, constraint = platform_treasury.key() == platform.authority)]
-    pub platform_treasury: AccountInfo<'info>,
-    #[account(mut)]
-    pub client: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Mark a finished task's losing submissions `NotSelected`, optionally closing them (permissionless)
+    ///
+    /// `remaining_accounts` are `[submission, agent_profile, agent_wallet]` triples. On an open
+    /// commit-reveal task, submissions left sealed past the reveal window can be marked early.
+    pub fn finalize_submissions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeSubmissions<'info>>,
+        close: bool,
+    ) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let task_key = task.key();
 
-#[derive(Accounts)]
-pub struct CancelTask<'info> {
-    #[account(mut, constraint = task.client == client.key())]
-    pub task: Account<'info, Task>,
-    /// CHECK: Escrow
-    #[account(mut, seeds = [b"escrow", task.id.to_le_bytes().as_ref()], bump = task.escrow_bump)]
-    pub escrow: AccountInfo<'info>,
-    #[account(mut)]
-    pub client: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let now = Clock::get()?.unix_timestamp;
+        if close {
+            require_closable(task, &ctx.accounts.platform)?;
+        } else {
+            require!(task.is_terminal() || task.commit_reveal, OpenfourrError::TaskNotFinished);
+        }
+
+        for triple in account_groups(ctx.remaining_accounts, 3)? {
+            let mut submission = Account::<Submission>::try_from(&triple[0])?;
+            let agent_wallet = &triple[2];
+
+            let expected = Pubkey::create_program_address(
+                &[b"submission", task_key.as_ref(), submission.agent.as_ref(), &[submission.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| OpenfourrError::InvalidRemainingAccounts)?;
+            require_keys_eq!(submission.key(), expected, OpenfourrError::InvalidRemainingAccounts);
+
+            if submission.status == SubmissionStatus::Pending {
+                // Before the task ends, only forfeited commitments lose
+                if !task.is_terminal() {
+                    require!(
+                        !submission.is_revealed() && task.reveal_window_closed(now)?,
+                        OpenfourrError::TaskNotFinished
+                    );
+                }
+                let mut agent = Account::<AgentProfile>::try_from(&triple[1])?;
+                require_keys_eq!(agent.owner, submission.agent, OpenfourrError::AgentMismatch);
+
+                submission.status = SubmissionStatus::NotSelected;
+                agent.open_submissions = agent.open_submissions.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+                agent.exit(&crate::ID)?;
+
+                emit_cpi!(SubmissionNotSelected { task: task.key(), task_id: task.id, submission: submission.key(), agent: submission.agent });
+            }
+
+            if close {
+                require_keys_eq!(agent_wallet.key(), submission.agent, OpenfourrError::AgentMismatch);
+                require_rating_window_closed(task, &submission)?;
+                submission.close(agent_wallet.clone())?;
+                task.closed_submissions = task.closed_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+                emit_cpi!(SubmissionClosed { task: task.key(), task_id: task.id, submission: submission.key(), agent: submission.agent });
+            } else {
+                submission.exit(&crate::ID)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close a message on a finished task, refunding rent to its sender (permissionless)
+    pub fn close_message(ctx: Context<CloseMessage>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+
+        require_closable(task, &ctx.accounts.platform)?;
+
+        task.closed_messages = task.closed_messages.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(MessageClosed { task: task.key(), task_id: task.id, message_id: ctx.accounts.message.message_id });
+        Ok(())
+    }
+
+    /// Close many messages of a finished task at once (permissionless)
+    ///
+    /// `remaining_accounts` are `[message, sender]` pairs.
+    pub fn close_messages<'info>(ctx: Context<'_, '_, 'info, 'info, CloseMessages<'info>>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let task_key = task.key();
+
+        require_closable(task, &ctx.accounts.platform)?;
+        for pair in account_groups(ctx.remaining_accounts, 2)? {
+            let message = Account::<Message>::try_from(&pair[0])?;
+            let sender = &pair[1];
+
+            let expected = Pubkey::create_program_address(
+                &[b"message", task_key.as_ref(), message.message_id.to_le_bytes().as_ref(), &[message.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| OpenfourrError::InvalidRemainingAccounts)?;
+            require_keys_eq!(message.key(), expected, OpenfourrError::InvalidRemainingAccounts);
+            require_keys_eq!(sender.key(), message.sender, OpenfourrError::InvalidRemainingAccounts);
+
+            message.close(sender.clone())?;
+            task.closed_messages = task.closed_messages.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+            emit_cpi!(MessageClosed { task: task.key(), task_id: task.id, message_id: message.message_id });
+        }
+        Ok(())
+    }
+
+    /// Close a finished task once its submissions and messages are closed (permissionless)
+    pub fn close_task(ctx: Context<CloseTask>) -> Result<()> {
+        let task = &ctx.accounts.task;
+
+        require_closable(task, &ctx.accounts.platform)?;
+        require!(task.closed_submissions == task.submission_count, OpenfourrError::HasSubmissions);
+        require!(task.refunds_claimed == task.contributor_count, OpenfourrError::HasContributions);
+        require!(task.closed_messages == task.message_count, OpenfourrError::HasMessages);
+        require!(task.bid_count == 0, OpenfourrError::HasBids);
+
+        if task.milestone_count > 0 {
+            let plan = ctx.accounts.milestone_plan.as_ref().ok_or(OpenfourrError::MilestonePlanRequired)?;
+            require_keys_eq!(plan.task, task.key(), OpenfourrError::MilestonePlanRequired);
+            plan.close(ctx.accounts.client.to_account_info())?;
+        }
+
+        emit_cpi!(TaskClosed { task: task.key(), task_id: task.id });
+        Ok(())
+    }
+
+    /// Rate the client of a completed task (by winning agent, once)
+    pub fn rate_client(ctx: Context<RateClient>, rating: u8) -> Result<()> {
+        let task = &ctx.accounts.task;
+        let submission = &mut ctx.accounts.submission;
+        let client_profile = &mut ctx.accounts.client_profile;
+
+        require!(task.status == TaskStatus::Completed, OpenfourrError::TaskNotFinished);
+        require!(submission.status == SubmissionStatus::Selected, OpenfourrError::NotWinner);
+        require!(submission.client_rating.is_none(), OpenfourrError::AlreadyRated);
+        require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+
+        submission.client_rating = Some(rating);
+        client_profile.adopt(task.client);
+        client_profile.rating_sum = client_profile.rating_sum.checked_add(rating as u64).ok_or(OpenfourrError::MathOverflow)?;
+        client_profile.rating_count = client_profile.rating_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(ClientRated { task: task.key(), task_id: task.id, client: task.client, agent: submission.agent, rating });
+        Ok(())
+    }
+
+    /// Claim an exclusive-claim task, or a bidding task after winning it (by agent)
+    pub fn claim_task(ctx: Context<ClaimTask>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let agent = &mut ctx.accounts.agent_profile;
+        let submission = &mut ctx.accounts.submission;
+
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode != TaskMode::OpenCompetition, OpenfourrError::WrongTaskMode);
+        require!(agent.is_active, OpenfourrError::AgentNotActive);
+        require!(task.is_invited(&agent.owner), OpenfourrError::NotInvited);
+        require!(agent.staked >= task.min_stake, OpenfourrError::InsufficientStake);
+        require!(Clock::get()?.unix_timestamp < task.deadline, OpenfourrError::TaskExpired);
+        match task.assigned_agent {
+            // Bidding tasks can only be claimed by the agent whose bid was accepted
+            Some(assigned) => require_keys_eq!(assigned, agent.owner, OpenfourrError::NotAssignedAgent),
+            None => require!(task.mode == TaskMode::ExclusiveClaim, OpenfourrError::NoAcceptedBid),
+        }
+
+        submission.task_id = task.id;
+        submission.task = task.key();
+        submission.agent = agent.owner;
+        submission.submission_url = String::new();
+        submission.submission_notes = String::new();
+        submission.submitted_at = 0;
+        submission.status = SubmissionStatus::Pending;
+        submission.bump = ctx.bumps.submission;
+
+        task.status = TaskStatus::InProgress;
+        task.assigned_agent = Some(agent.owner);
+        task.submission_count = task.submission_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        agent.open_submissions = agent.open_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(TaskClaimed { task: task.key(), task_id: task.id, submission: submission.key(), agent: agent.owner });
+        Ok(())
+    }
+
+    /// Bid on a bidding-mode task (by agent)
+    pub fn submit_bid(ctx: Context<SubmitBid>, price: u64, eta_hours: u64) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let agent = &ctx.accounts.agent_profile;
+        let bid = &mut ctx.accounts.bid;
+
+        require!(!ctx.accounts.platform.paused, OpenfourrError::PlatformPaused);
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode == TaskMode::Bidding, OpenfourrError::WrongTaskMode);
+        require!(task.assigned_agent.is_none(), OpenfourrError::BidAlreadyAccepted);
+        require!(agent.is_active, OpenfourrError::AgentNotActive);
+        require!(task.is_invited(&agent.owner), OpenfourrError::NotInvited);
+        require!(agent.staked >= task.min_stake, OpenfourrError::InsufficientStake);
+        require!(Clock::get()?.unix_timestamp < task.deadline, OpenfourrError::TaskExpired);
+        require!(price > 0 && price <= task.bounty_amount, OpenfourrError::InvalidBidPrice);
+        require!(eta_hours > 0, OpenfourrError::InvalidDeadline);
+
+        bid.task = task.key();
+        bid.agent = agent.owner;
+        bid.price = price;
+        bid.eta_hours = eta_hours;
+        bid.submitted_at = Clock::get()?.unix_timestamp;
+        bid.bump = ctx.bumps.bid;
+
+        task.bid_count = task.bid_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(BidSubmitted {
+            task: task.key(),
+            task_id: task.id,
+            bid: bid.key(),
+            agent: agent.owner,
+            price,
+            eta_hours,
+            submitted_at: bid.submitted_at,
+        });
+        Ok(())
+    }
+
+    /// Withdraw a pending bid and reclaim its rent (by agent)
+    pub fn withdraw_bid(ctx: Context<WithdrawBid>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let bid = &ctx.accounts.bid;
+
+        task.bid_count = task.bid_count.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(BidWithdrawn { task: task.key(), task_id: task.id, bid: bid.key(), agent: bid.agent });
+        Ok(())
+    }
+
+    /// Close a losing bid once another was accepted or the task ended, refunding rent to its bidder (permissionless)
+    pub fn close_bid(ctx: Context<CloseBid>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let bid = &ctx.accounts.bid;
+
+        require!(task.assigned_agent.is_some() || task.is_terminal(), OpenfourrError::BiddingOpen);
+
+        task.bid_count = task.bid_count.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(BidClosed { task: task.key(), task_id: task.id, bid: bid.key(), agent: bid.agent });
+        Ok(())
+    }
+
+    /// Accept a bid, refunding the unused budget from escrow and returning the bid's rent to the agent (by client)
+    pub fn accept_bid(ctx: Context<AcceptBid>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let bid = &ctx.accounts.bid;
+
+        require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+        require!(task.mode == TaskMode::Bidding, OpenfourrError::WrongTaskMode);
+        require!(task.assigned_agent.is_none(), OpenfourrError::BidAlreadyAccepted);
+        require!(bid.price <= task.bounty_amount, OpenfourrError::InvalidBidPrice);
+
+        let refund = task.bounty_amount.checked_sub(bid.price).ok_or(OpenfourrError::MathOverflow)?;
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.client,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+        vault.pay(&ctx.accounts.client, ctx.accounts.client_token_account.as_deref(), refund)?;
+
+        task.bounty_amount = bid.price;
+        task.assigned_agent = Some(bid.agent);
+        task.bid_count = task.bid_count.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        emit_cpi!(BidAccepted { task: task.key(), task_id: task.id, bid: bid.key(), agent: bid.agent, price: bid.price, refund });
+        Ok(())
+    }
+
+    /// Submit work on a claimed task (by assigned agent)
+    pub fn submit_work(
+        ctx: Context<SubmitWork>,
+        submission_url: String,
+        submission_notes: String,
+    ) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let submission = &mut ctx.accounts.submission;
+
+        require!(task.status == TaskStatus::InProgress, OpenfourrError::TaskNotInProgress);
+        require!(task.assigned_agent == Some(submission.agent), OpenfourrError::NotAssignedAgent);
+        require!(Clock::get()?.unix_timestamp < task.deadline, OpenfourrError::TaskExpired);
+        validate_submission(&submission_url, &submission_notes)?;
+
+        submission.submission_url = submission_url;
+        submission.submission_notes = submission_notes;
+        submission.submitted_at = Clock::get()?.unix_timestamp;
+
+        task.status = TaskStatus::PendingReview;
+
+        emit_cpi!(WorkSubmitted {
+            task: task.key(),
+            task_id: task.id,
+            submission: submission.key(),
+            agent: submission.agent,
+            submission_url: submission.submission_url.clone(),
+            submission_notes: submission.submission_notes.clone(),
+            submitted_at: submission.submitted_at,
+        });
+        Ok(())
+    }
+
+    /// Approve submitted work and release escrow (by client)
+    pub fn approve_work(ctx: Context<SelectWinner>, rating: u8) -> Result<()> {
+        let task = &ctx.accounts.task;
+
+        require!(task.status == TaskStatus::PendingReview, OpenfourrError::TaskNotPendingReview);
+        require!(task.assigned_agent == Some(ctx.accounts.submission.agent), OpenfourrError::NotAssignedAgent);
+
+        let selected = ctx.accounts.pay_winner(rating)?;
+        emit_cpi!(selected);
+        Ok(())
+    }
+
+    /// Reject submitted work (by client)
+    pub fn reject_work(ctx: Context<RejectWork>, reason: String) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let agent = &mut ctx.accounts.agent_profile;
+
+        require!(task.status == TaskStatus::PendingReview, OpenfourrError::TaskNotPendingReview);
+        require!(task.assigned_agent == Some(agent.owner), OpenfourrError::NotAssignedAgent);
+        require!(task.verdict.is_none(), OpenfourrError::VerdictPending);
+        validate_reason(&reason)?;
+
+        record_rejection(task, &mut ctx.accounts.submission, agent)?;
+
+        emit_cpi!(WorkRejected {
+            task: task.key(),
+            task_id: task.id,
+            submission: ctx.accounts.submission.key(),
+            agent: agent.owner,
+            reason,
+            rejection_count: task.rejection_count,
+        });
+        Ok(())
+    }
+
+    /// Approve or reject a submission on a judged task (by task judge)
+    pub fn judge_verdict(ctx: Context<JudgeVerdict>, approved: bool, rating: u8, reason: String) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let submission = &mut ctx.accounts.submission;
+        let agent = &mut ctx.accounts.agent_profile;
+        let judge = ctx.accounts.judge.key();
+
+        require!(task.judge == Some(judge), OpenfourrError::NotJudge);
+        require!(task.verdict.is_none(), OpenfourrError::VerdictPending);
+        require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+        require!(submission.is_revealed(), OpenfourrError::SubmissionNotRevealed);
+        match task.mode {
+            TaskMode::OpenCompetition => {
+                require!(task.status == TaskStatus::Open, OpenfourrError::TaskNotOpen);
+            }
+            TaskMode::ExclusiveClaim | TaskMode::Bidding => {
+                require!(task.status == TaskStatus::PendingReview, OpenfourrError::TaskNotPendingReview);
+                require!(task.assigned_agent == Some(submission.agent), OpenfourrError::NotAssignedAgent);
+            }
+        }
+
+        if approved {
+            require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+            require!(task.milestone_count == 0, OpenfourrError::MilestoneTask);
+            require_reveals_settled(task)?;
+
+            let issued_at = Clock::get()?.unix_timestamp;
+            task.verdict = Some(Verdict { submission: submission.key(), rating, issued_at });
+
+            emit_cpi!(VerdictIssued {
+                task: task.key(),
+                task_id: task.id,
+                judge,
+                submission: submission.key(),
+                agent: submission.agent,
+                rating,
+                challenge_ends: issued_at.checked_add(JUDGE_CHALLENGE_WINDOW).ok_or(OpenfourrError::MathOverflow)?,
+            });
+        } else {
+            validate_reason(&reason)?;
+
+            let slashed = slash_stake(
+                agent,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.client,
+                &ctx.accounts.system_program,
+                ctx.accounts.platform.slash_bps,
+            )?;
+            if slashed > 0 {
+                emit_cpi!(StakeSlashed { agent: agent.owner, amount: slashed, destination: ctx.accounts.client.key() });
+            }
+
+            match task.mode {
+                TaskMode::OpenCompetition => {
+                    submission.status = SubmissionStatus::NotSelected;
+                    agent.open_submissions = agent.open_submissions.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+                }
+                TaskMode::ExclusiveClaim | TaskMode::Bidding => record_rejection(task, submission, agent)?,
+            }
+
+            emit_cpi!(WorkRejected {
+                task: task.key(),
+                task_id: task.id,
+                submission: submission.key(),
+                agent: submission.agent,
+                reason,
+                rejection_count: task.rejection_count,
+            });
+        }
+        Ok(())
+    }
+
+    /// Release escrow per the judge's verdict once the challenge window has passed (permissionless)
+    pub fn finalize_verdict(ctx: Context<FinalizeVerdict>) -> Result<()> {
+        let task = &mut ctx.accounts.task;
+        let submission = &mut ctx.accounts.submission;
+        let agent = &mut ctx.accounts.agent_profile;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(!platform.paused, OpenfourrError::PlatformPaused);
+
+        let verdict = task.verdict.ok_or(OpenfourrError::NoVerdict)?;
+        require!(
+            matches!(task.status, TaskStatus::Open | TaskStatus::PendingReview),
+            OpenfourrError::TaskNotOpen
+        );
+        require_keys_eq!(submission.key(), verdict.submission, OpenfourrError::VerdictSubmissionMismatch);
+        require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+        if let Some(leader) = task.binding_vote() {
+            require_keys_eq!(submission.key(), leader, OpenfourrError::OverruledByFunders);
+        }
+        require!(
+            Clock::get()?.unix_timestamp >= verdict.issued_at.checked_add(JUDGE_CHALLENGE_WINDOW).ok_or(OpenfourrError::MathOverflow)?,
+            OpenfourrError::ChallengeWindowOpen
+        );
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payer,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+        let (payout, _) = vault.pay_agent(
+            platform,
+            task.bounty_amount,
+            &ctx.accounts.agent_wallet,
+            ctx.accounts.agent_token_account.as_deref(),
+            &ctx.accounts.platform_treasury,
+            ctx.accounts.treasury_token_account.as_deref(),
+        )?;
+        vault.close(&ctx.accounts.client, ctx.accounts.client_token_account.as_deref())?;
+
+        let gross = task.bounty_amount;
+        record_win(task, submission, agent, platform, &mut ctx.accounts.client_profile, gross, payout, Some(verdict.rating))?;
+
+        emit_cpi!(WinnerSelected {
+            task: task.key(),
+            task_id: task.id,
+            submission: submission.key(),
+            agent: agent.owner,
+            payout,
+            rating: verdict.rating,
+        });
+        Ok(())
+    }
+
+    /// Resolve a dispute (by platform authority or arbiter)
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        resolution: DisputeResolution,
+        rating: Option<u8>,
+        slash: Option<SlashTarget>,
+        return_bond: bool,
+    ) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let task = &mut ctx.accounts.task;
+        let arbiter = ctx.accounts.arbiter.key();
+
+        require!(
+            arbiter == platform.authority || platform.arbiter == Some(arbiter),
+            OpenfourrError::NotArbiter
+        );
+        require!(task.status == TaskStatus::Disputed, OpenfourrError::TaskNotDisputed);
+        if let Some(rating) = rating {
+            require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+        }
+
+        let remaining = task.remaining_bounty()?;
+        let agent_share = match resolution {
+            DisputeResolution::AwardSubmission => remaining,
+            DisputeResolution::RefundClient => 0,
+            DisputeResolution::Split { agent_bps } => {
+                require!(agent_bps <= 10000, OpenfourrError::InvalidSplit);
+                bps_of(remaining, agent_bps)?
+            }
+        };
+        let refund = remaining.checked_sub(agent_share).ok_or(OpenfourrError::MathOverflow)?;
+
+        let vault = EscrowVault::new(
+            task,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            &ctx.accounts.arbiter,
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            ctx.accounts.associated_token_program.as_ref(),
+        )?;
+
+        let mut agent_key = None;
+        let mut payout = 0;
+        let mut fee = 0;
+
+        if agent_share > 0 {
+            let submission = ctx.accounts.submission.as_mut().ok_or(OpenfourrError::SubmissionRequired)?;
+            let agent = ctx.accounts.agent_profile.as_mut().ok_or(OpenfourrError::SubmissionRequired)?;
+            let agent_wallet = ctx.accounts.agent_wallet.as_ref().ok_or(OpenfourrError::SubmissionRequired)?;
+
+            require!(submission.task == task.key(), OpenfourrError::SubmissionTaskMismatch);
+            require!(submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+            require!(submission.is_revealed(), OpenfourrError::SubmissionNotRevealed);
+            require!(
+                agent.owner == submission.agent && agent_wallet.key() == submission.agent,
+                OpenfourrError::AgentMismatch
+            );
+
+            (payout, fee) = vault.pay_agent(
+                platform,
+                agent_share,
+                agent_wallet,
+                ctx.accounts.agent_token_account.as_deref(),
+                &ctx.accounts.platform_treasury,
+                ctx.accounts.treasury_token_account.as_deref(),
+            )?;
+
+            record_win(task, submission, agent, platform, &mut ctx.accounts.client_profile, agent_share, payout, rating)?;
+            agent_key = Some(agent.owner);
+        } else {
+            record_cancel(task, &mut ctx.accounts.client_profile)?;
+        }
+
+        vault.refund(task, &ctx.accounts.client, ctx.accounts.client_token_account.as_deref(), refund)?;
+
+        let mut slashed = 0;
+        if let Some(target) = slash {
+            let submission = ctx.accounts.submission.as_ref().ok_or(OpenfourrError::SubmissionRequired)?;
+            let agent = ctx.accounts.agent_profile.as_mut().ok_or(OpenfourrError::SubmissionRequired)?;
+            let stake_vault = ctx.accounts.stake_vault.as_ref().ok_or(OpenfourrError::StakeVaultRequired)?;
+
+            require!(submission.task == task.key(), OpenfourrError::SubmissionTaskMismatch);
+            require_keys_eq!(agent.owner, submission.agent, OpenfourrError::AgentMismatch);
+
+            let destination = match target {
+                SlashTarget::Client => ctx.accounts.client.to_account_info(),
+                SlashTarget::Treasury => ctx.accounts.platform_treasury.to_account_info(),
+            };
+            slashed = slash_stake(agent, stake_vault, &destination, &ctx.accounts.system_program, platform.slash_bps)?;
+            if slashed > 0 {
+                emit_cpi!(StakeSlashed { agent: agent.owner, amount: slashed, destination: destination.key() });
+            }
+        }
+
+        // A forfeited bond goes to the treasury; the dispute's rent still returns to the raiser on close
+        let dispute = &ctx.accounts.dispute;
+        let bond_forfeited = if return_bond { 0 } else { dispute.bond };
+        if bond_forfeited > 0 {
+            dispute.sub_lamports(bond_forfeited)?;
+            ctx.accounts.platform_treasury.add_lamports(bond_forfeited)?;
+        }
+
+        emit_cpi!(DisputeResolved {
+            task: task.key(),
+            task_id: task.id,
+            dispute: dispute.key(),
+            arbiter,
+            resolution,
+            agent: agent_key,
+            payout,
+            fee,
+            refund,
+            slashed,
+            bond_forfeited,
+        });
+        Ok(())
+    }
+
+    // Accounts opened by the first devnet deployment (v0) keep their old, shorter layout until
+    // migrated. Anyone can run these; the payer covers the extra rent.
+
+    /// Rewrite the v0 platform in the current layout
+    pub fn migrate_platform(ctx: Context<MigratePlatform>) -> Result<()> {
+        let info = ctx.accounts.platform.to_account_info();
+        let legacy = read_legacy::<PlatformV0>(&info, Platform::DISCRIMINATOR)?;
+
+        let migrated = write_migrated(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &legacy.migrate())?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    /// Rewrite a v0 task in the current layout and top its escrow up to the rent reserve SOL escrows now keep
+    pub fn migrate_task(ctx: Context<MigrateTask>) -> Result<()> {
+        let info = ctx.accounts.task.to_account_info();
+        let escrow = &ctx.accounts.escrow;
+        let legacy = read_legacy::<TaskV0>(&info, Task::DISCRIMINATOR)?;
+
+        let expected_escrow = Pubkey::create_program_address(
+            &[b"escrow", legacy.id.to_le_bytes().as_ref(), &[legacy.escrow_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| OpenfourrError::NotLegacyAccount)?;
+        require_keys_eq!(escrow.key(), expected_escrow, OpenfourrError::NotLegacyAccount);
+
+        let task = legacy.migrate();
+        if !task.is_terminal() {
+            let reserve = Rent::get()?.minimum_balance(0);
+            let held = task.remaining_bounty()?.checked_add(reserve).ok_or(OpenfourrError::MathOverflow)?;
+            let shortfall = held.saturating_sub(escrow.lamports());
+            if shortfall > 0 {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: escrow.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+            }
+        }
+
+        let migrated = write_migrated(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &task)?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    /// Rewrite a v0 agent profile in the current layout; `open_submissions` is rebuilt by `migrate_submission`
+    pub fn migrate_agent(ctx: Context<MigrateAgent>) -> Result<()> {
+        let info = ctx.accounts.agent_profile.to_account_info();
+        let legacy = read_legacy::<AgentProfileV0>(&info, AgentProfile::DISCRIMINATOR)?;
+
+        let migrated = write_migrated(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &legacy.migrate())?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    /// Rewrite a v0 submission in the current layout, counting it towards its agent's `open_submissions` while pending
+    pub fn migrate_submission(ctx: Context<MigrateSubmission>) -> Result<()> {
+        let info = ctx.accounts.submission.to_account_info();
+        let task = &ctx.accounts.task;
+        let agent = &mut ctx.accounts.agent_profile;
+        let legacy = read_legacy::<SubmissionV0>(&info, Submission::DISCRIMINATOR)?;
+
+        let expected = Pubkey::create_program_address(
+            &[b"submission", task.key().as_ref(), legacy.agent.as_ref(), &[legacy.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| OpenfourrError::NotLegacyAccount)?;
+        require_keys_eq!(info.key(), expected, OpenfourrError::SubmissionTaskMismatch);
+        require_keys_eq!(agent.owner, legacy.agent, OpenfourrError::AgentMismatch);
+
+        let submission = legacy.migrate(task.key());
+        if submission.status == SubmissionStatus::Pending {
+            agent.open_submissions = agent.open_submissions.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        }
+
+        let migrated = write_migrated(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &submission)?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    /// Rewrite a v0 message in the current layout
+    pub fn migrate_message(ctx: Context<MigrateMessage>) -> Result<()> {
+        let info = ctx.accounts.message.to_account_info();
+        let task = &ctx.accounts.task;
+        let legacy = read_legacy::<MessageV0>(&info, Message::DISCRIMINATOR)?;
+
+        let expected = Pubkey::create_program_address(
+            &[b"message", task.key().as_ref(), legacy.message_id.to_le_bytes().as_ref(), &[legacy.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| OpenfourrError::NotLegacyAccount)?;
+        require_keys_eq!(info.key(), expected, OpenfourrError::NotLegacyAccount);
+
+        let migrated = write_migrated(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &legacy.migrate(task.key()))?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+}
+
+// ============ HELPERS ============
+
+impl<'info> SelectWinner<'info> {
+    /// Pay the bounty to `submission` and record the completed task
+    fn pay_winner(&mut self, rating: u8) -> Result<WinnerSelected> {
+        require!(!self.platform.paused, OpenfourrError::PlatformPaused);
+        require!((1..=5).contains(&rating), OpenfourrError::InvalidRating);
+        require!(self.submission.status == SubmissionStatus::Pending, OpenfourrError::SubmissionNotPending);
+        require!(self.submission.is_revealed(), OpenfourrError::SubmissionNotRevealed);
+        require!(self.task.milestone_count == 0, OpenfourrError::MilestoneTask);
+        require!(self.task.verdict.is_none(), OpenfourrError::VerdictPending);
+        require_reveals_settled(&self.task)?;
+        if let Some(leader) = self.task.binding_vote() {
+            require_keys_eq!(self.submission.key(), leader, OpenfourrError::OverruledByFunders);
+        }
+
+        let vault = EscrowVault::new(
+            &self.task,
+            &self.escrow,
+            &self.system_program,
+            &self.client,
+            self.mint.as_ref(),
+            self.escrow_token_account.as_ref(),
+            self.token_program.as_ref(),
+            self.associated_token_program.as_ref(),
+        )?;
+        let (payout, _) = vault.pay_agent(
+            &mut self.platform,
+            self.task.bounty_amount,
+            &self.agent_wallet,
+            self.agent_token_account.as_deref(),
+            &self.platform_treasury,
+            self.treasury_token_account.as_deref(),
+        )?;
+        vault.close(&self.client, self.client_token_account.as_deref())?;
+
+        let gross = self.task.bounty_amount;
+        record_win(
+            &mut self.task,
+            &mut self.submission,
+            &mut self.agent_profile,
+            &mut self.platform,
+            &mut self.client_profile,
+            gross,
+            payout,
+            Some(rating),
+        )?;
+
+        Ok(WinnerSelected {
+            task: self.task.key(),
+            task_id: self.task.id,
+            submission: self.submission.key(),
+            agent: self.agent_profile.owner,
+            payout,
+            rating,
+        })
+    }
+}
+
+/// Mark `submission` as the winner and credit the agent, client and platform stats
+#[allow(clippy::too_many_arguments)]
+fn record_win(
+    task: &mut Task,
+    submission: &mut Account<Submission>,
+    agent: &mut AgentProfile,
+    platform: &mut Platform,
+    client_profile: &mut ClientProfile,
+    gross: u64,
+    payout: u64,
+    rating: Option<u8>,
+) -> Result<()> {
+    task.status = TaskStatus::Completed;
+    task.winning_submission = Some(submission.key());
+    task.completed_at = Some(Clock::get()?.unix_timestamp);
+    platform.total_completed = platform.total_completed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    record_client_completion(task, client_profile, gross)?;
+
+    credit_winner(task, submission, agent, platform, gross, payout, rating)
+}
+
+/// Count a completed task for its client, with `gross` as the final payment out of escrow
+fn record_client_completion(task: &Task, client_profile: &mut ClientProfile, gross: u64) -> Result<()> {
+    client_profile.adopt(task.client);
+    client_profile.tasks_completed = client_profile.tasks_completed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    credit_client(task, client_profile, gross)
+}
+
+/// Add a payment out of a SOL task's escrow to the client's `total_paid`
+fn credit_client(task: &Task, client_profile: &mut ClientProfile, gross: u64) -> Result<()> {
+    client_profile.adopt(task.client);
+    if task.mint.is_none() {
+        client_profile.total_paid = client_profile.total_paid.checked_add(gross).ok_or(OpenfourrError::MathOverflow)?;
+    }
+    Ok(())
+}
+
+/// Cancel a refunded task and count it against its client
+fn record_cancel(task: &mut Task, client_profile: &mut ClientProfile) -> Result<()> {
+    task.status = TaskStatus::Cancelled;
+    task.completed_at = Some(Clock::get()?.unix_timestamp);
+    client_profile.adopt(task.client);
+    client_profile.tasks_cancelled = client_profile.tasks_cancelled.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    Ok(())
+}
+
+/// Mark one winning submission `Selected` and credit its agent; task-level stats are left to the caller
+fn credit_winner(
+    task: &Task,
+    submission: &mut Submission,
+    agent: &mut AgentProfile,
+    platform: &mut Platform,
+    gross: u64,
+    payout: u64,
+    rating: Option<u8>,
+) -> Result<()> {
+    submission.status = SubmissionStatus::Selected;
+
+    agent.tasks_completed = agent.tasks_completed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    agent.open_submissions = agent.open_submissions.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+    if let Some(rating) = rating {
+        agent.rating_sum = agent.rating_sum.checked_add(rating as u64).ok_or(OpenfourrError::MathOverflow)?;
+        agent.rating_count = agent.rating_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    }
+
+    if task.mint.is_none() {
+        agent.total_earned = agent.total_earned.checked_add(payout).ok_or(OpenfourrError::MathOverflow)?;
+        platform.total_volume = platform.total_volume.checked_add(gross).ok_or(OpenfourrError::MathOverflow)?;
+    }
+    Ok(())
+}
+
+/// `amount * numerator / denominator`, widened to u128 so the product can't overflow
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|product| product.checked_div(denominator as u128))
+        .ok_or(OpenfourrError::MathOverflow)?;
+    u64::try_from(value).map_err(|_| OpenfourrError::MathOverflow.into())
+}
+
+/// `bps` basis points of `amount`
+pub fn bps_of(amount: u64, bps: u16) -> Result<u64> {
+    mul_div(amount, bps as u64, 10000)
+}
+
+/// Split `gross` into `(payout, fee)` at the platform fee rate
+pub fn split_fee(gross: u64, fee_bps: u16) -> Result<(u64, u64)> {
+    let fee = bps_of(gross, fee_bps)?;
+    let payout = gross.checked_sub(fee).ok_or(OpenfourrError::MathOverflow)?;
+    Ok((payout, fee))
+}
+
+/// Unix timestamp `hours` after `start`
+pub fn hours_after(start: i64, hours: u64) -> Result<i64> {
+    i64::try_from(hours)
+        .ok()
+        .and_then(|hours| hours.checked_mul(3600))
+        .and_then(|seconds| start.checked_add(seconds))
+        .ok_or(OpenfourrError::MathOverflow.into())
+}
+
+/// Finished tasks can be closed once the platform retention period has passed
+fn require_closable(task: &Task, platform: &Platform) -> Result<()> {
+    require!(task.is_terminal(), OpenfourrError::TaskNotFinished);
+
+    let finished_at = task.completed_at.ok_or(OpenfourrError::TaskNotFinished)?;
+    require!(
+        Clock::get()?.unix_timestamp >= finished_at.checked_add(platform.retention_period).ok_or(OpenfourrError::MathOverflow)?,
+        OpenfourrError::RetentionPeriodActive
+    );
+    Ok(())
+}
+
+/// On commit-reveal tasks, winners are picked only once every sealed submission is revealed or the
+/// reveal window has closed, so an early reveal can't be paid while rivals are still sealed
+fn require_reveals_settled(task: &Task) -> Result<()> {
+    if task.commit_reveal && task.revealed_count < task.submission_count {
+        require!(task.reveal_window_closed(Clock::get()?.unix_timestamp)?, OpenfourrError::RevealWindowOpen);
+    }
+    Ok(())
+}
+
+/// A winner that hasn't rated the client yet keeps its submission, which `rate_client` needs,
+/// until the rating window has passed
+fn require_rating_window_closed(task: &Task, submission: &Submission) -> Result<()> {
+    if submission.status == SubmissionStatus::Selected && submission.client_rating.is_none() {
+        let finished_at = task.completed_at.ok_or(OpenfourrError::TaskNotFinished)?;
+        require!(
+            Clock::get()?.unix_timestamp >= finished_at.checked_add(CLIENT_RATING_WINDOW).ok_or(OpenfourrError::MathOverflow)?,
+            OpenfourrError::ClientRatingWindowActive
+        );
+    }
+    Ok(())
+}
+
+/// Split remaining accounts into fixed-size groups, rejecting a trailing partial group
+fn account_groups<'a, 'info>(accounts: &'a [AccountInfo<'info>], size: usize) -> Result<ChunksExact<'a, AccountInfo<'info>>> {
+    let groups = accounts.chunks_exact(size);
+    require!(groups.remainder().is_empty(), OpenfourrError::InvalidRemainingAccounts);
+    Ok(groups)
+}
+
+/// Move `slash_bps` of the agent's stake (cooling-down stake last) to `destination`; returns the amount slashed
+/// so the caller can emit `StakeSlashed`
+fn slash_stake<'info>(
+    agent: &mut AgentProfile,
+    stake_vault: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    slash_bps: u16,
+) -> Result<u64> {
+    let (expected_vault, stake_bump) = Pubkey::find_program_address(&[b"stake", agent.owner.as_ref()], &crate::ID);
+    require_keys_eq!(stake_vault.key(), expected_vault, OpenfourrError::InvalidStakeVault);
+
+    let total = agent.staked.checked_add(agent.unstaking).ok_or(OpenfourrError::MathOverflow)?;
+    let amount = bps_of(total, slash_bps)?;
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    let from_staked = amount.min(agent.staked);
+    agent.staked = agent.staked.checked_sub(from_staked).ok_or(OpenfourrError::MathOverflow)?;
+    agent.unstaking = agent.unstaking.checked_sub(amount.saturating_sub(from_staked)).ok_or(OpenfourrError::MathOverflow)?;
+
+    let owner_key = agent.owner;
+    let stake_seeds = &[b"stake".as_ref(), owner_key.as_ref(), &[stake_bump]];
+    let signer_seeds = &[&stake_seeds[..]];
+    let cpi_context = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: stake_vault.to_account_info(),
+            to: destination.to_account_info(),
+        },
+        signer_seeds,
+    );
+    anchor_lang::system_program::transfer(cpi_context, amount)?;
+    Ok(amount)
+}
+
+/// Hash a sealed submission binds to: `sha256(agent || url || 0 || notes || 0 || salt)`
+pub fn submission_commitment(agent: &Pubkey, submission_url: &str, submission_notes: &str, salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[agent.as_ref(), submission_url.as_bytes(), &[0], submission_notes.as_bytes(), &[0], salt]).to_bytes()
+}
+
+/// Count a rejection against an exclusive-claim task, failing it at `MAX_REJECTIONS`
+fn record_rejection(task: &mut Task, submission: &mut Submission, agent: &mut AgentProfile) -> Result<()> {
+    task.rejection_count = task.rejection_count.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+    if task.rejection_count >= MAX_REJECTIONS {
+        task.status = TaskStatus::Rejected;
+        submission.status = SubmissionStatus::NotSelected;
+        agent.tasks_failed = agent.tasks_failed.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+        agent.open_submissions = agent.open_submissions.checked_sub(1).ok_or(OpenfourrError::MathOverflow)?;
+    } else {
+        task.status = TaskStatus::InProgress;
+    }
+    Ok(())
+}
+
+/// Accounts and bumps shared by `create_task` and `create_client_task`
+struct NewTask<'a, 'info> {
+    task: &'a mut Account<'info, Task>,
+    client_profile: &'a mut Account<'info, ClientProfile>,
+    platform: &'a Platform,
+    escrow: &'a AccountInfo<'info>,
+    client: &'a Signer<'info>,
+    system_program: &'a Program<'info, System>,
+    mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    client_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    escrow_token_account: Option<&'a UncheckedAccount<'info>>,
+    token_program: Option<&'a Interface<'info, TokenInterface>>,
+    associated_token_program: Option<&'a Program<'info, AssociatedToken>>,
+    task_id: u64,
+    per_client: bool,
+    task_bump: u8,
+    escrow_bump: u8,
+    client_profile_bump: u8,
+}
+
+impl<'a, 'info> NewTask<'a, 'info> {
+    /// Fund the escrow and initialize the task and client profile
+    #[allow(clippy::too_many_arguments)]
+    fn open(
+        self,
+        title: String,
+        description: String,
+        requirements: String,
+        category: String,
+        bounty_amount: u64,
+        deadline_hours: u64,
+        options: TaskOptions,
+    ) -> Result<TaskCreated> {
+        validate_task_details(&title, &description, &requirements, &category, deadline_hours)?;
+        require!(bounty_amount > 0, OpenfourrError::InvalidBounty);
+        require!(
+            !options.commit_reveal || options.mode == TaskMode::OpenCompetition,
+            OpenfourrError::InvalidTaskOptions
+        );
+        require!(
+            !options.crowdfunded || options.mode == TaskMode::OpenCompetition,
+            OpenfourrError::InvalidTaskOptions
+        );
+        validate_payout_schedule(&options)?;
+        let invited_agents = match options.direct_hire {
+            Some(agent) => {
+                require!(options.invited_agents.is_empty(), OpenfourrError::InvalidTaskOptions);
+                vec![agent]
+            }
+            None => options.invited_agents,
+        };
+        validate_invites(&invited_agents)?;
+
+        let task = self.task;
+        let platform = self.platform;
+
+        require!(!platform.paused, OpenfourrError::PlatformPaused);
+
+        // Transfer bounty to escrow
+        match self.mint {
+            Some(mint) => {
+                let client_token_account = self.client_token_account.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let escrow_token_account = self.escrow_token_account.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let token_program = self.token_program.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let associated_token_program = self.associated_token_program.ok_or(OpenfourrError::TokenAccountRequired)?;
+
+                require_keys_eq!(
+                    escrow_token_account.key(),
+                    get_associated_token_address_with_program_id(&self.escrow.key(), &mint.key(), &token_program.key()),
+                    OpenfourrError::InvalidEscrowTokenAccount
+                );
+
+                let create_context = CpiContext::new(
+                    associated_token_program.to_account_info(),
+                    associated_token::Create {
+                        payer: self.client.to_account_info(),
+                        associated_token: escrow_token_account.to_account_info(),
+                        authority: self.escrow.to_account_info(),
+                        mint: mint.to_account_info(),
+                        system_program: self.system_program.to_account_info(),
+                        token_program: token_program.to_account_info(),
+                    },
+                );
+                associated_token::create_idempotent(create_context)?;
+
+                let cpi_context = CpiContext::new(
+                    token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: client_token_account.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: escrow_token_account.to_account_info(),
+                        authority: self.client.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(cpi_context, bounty_amount, mint.decimals)?;
+
+                // Mints with a transfer fee would leave the escrow short of the bounty
+                let escrow_balance = TokenAccount::try_deserialize(&mut &escrow_token_account.try_borrow_data()?[..])?.amount;
+                require!(escrow_balance >= bounty_amount, OpenfourrError::EscrowUnderfunded);
+            }
+            None => {
+                // The escrow keeps a rent-exempt reserve so partial payouts never strand it below the minimum;
+                // `EscrowVault::close` sweeps it back to the client
+                let reserve = Rent::get()?.minimum_balance(0).saturating_sub(self.escrow.lamports());
+                let cpi_context = CpiContext::new(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.client.to_account_info(),
+                        to: self.escrow.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(
+                    cpi_context,
+                    bounty_amount.checked_add(reserve).ok_or(OpenfourrError::MathOverflow)?,
+                )?;
+            }
+        }
+
+        task.id = self.task_id;
+        task.client = self.client.key();
+        task.title = title;
+        task.description = description;
+        task.requirements = requirements;
+        task.category = category;
+        task.bounty_amount = bounty_amount;
+        task.created_at = Clock::get()?.unix_timestamp;
+        task.deadline = hours_after(Clock::get()?.unix_timestamp, deadline_hours)?;
+        task.status = TaskStatus::Open;
+        task.submission_count = 0;
+        task.message_count = 0;
+        task.extension_requested = false;
+        task.extension_hours = 0;
+        task.dispute_raised_by = None;
+        task.winning_submission = None;
+        task.completed_at = None;
+        task.bump = self.task_bump;
+        task.escrow_bump = self.escrow_bump;
+        task.per_client = self.per_client;
+        task.bid_count = 0;
+        task.revealed_count = 0;
+        task.mint = self.mint.map(|mint| mint.key());
+        task.mode = options.mode;
+        task.assigned_agent = None;
+        task.rejection_count = 0;
+        task.judge = if options.use_judge {
+            Some(platform.judge.ok_or(OpenfourrError::NoJudgeConfigured)?)
+        } else {
+            None
+        };
+        task.verdict = None;
+        task.commit_reveal = options.commit_reveal;
+        task.milestone_count = 0;
+        task.released_amount = 0;
+        task.closed_submissions = 0;
+        task.closed_messages = 0;
+        task.invite_only = !invited_agents.is_empty();
+        task.invited_agents = invited_agents;
+        task.crowdfunded = options.crowdfunded;
+        task.contributed = 0;
+        task.contributor_count = 0;
+        task.vote_leader = None;
+        task.vote_leader_votes = 0;
+        task.refund_pool = 0;
+        task.refunds_paid = 0;
+        task.refunds_claimed = 0;
+        task.min_stake = options.min_stake;
+        task.payout_schedule = options.payout_schedule;
+
+        let client_profile = self.client_profile;
+        if client_profile.owner == Pubkey::default() {
+            client_profile.owner = task.client;
+            client_profile.bump = self.client_profile_bump;
+        }
+        client_profile.tasks_posted = client_profile.tasks_posted.checked_add(1).ok_or(OpenfourrError::MathOverflow)?;
+
+        Ok(TaskCreated {
+            task: task.key(),
+            task_id: task.id,
+            client: task.client,
+            escrow: self.escrow.key(),
+            title: task.title.clone(),
+            description: task.description.clone(),
+            requirements: task.requirements.clone(),
+            category: task.category.clone(),
+            bounty: task.bounty_amount,
+            mint: task.mint,
+            mode: task.mode,
+            judge: task.judge,
+            commit_reveal: task.commit_reveal,
+            crowdfunded: task.crowdfunded,
+            invited_agents: task.invited_agents.clone(),
+            min_stake: task.min_stake,
+            payout_schedule: task.payout_schedule.clone(),
+            created_at: task.created_at,
+            deadline: task.deadline,
+        })
+    }
+}
+
+/// SPL token side of an escrow; absent for native SOL tasks
+struct TokenVault<'a, 'info> {
+    mint: &'a InterfaceAccount<'info, Mint>,
+    escrow_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+    token_program: &'a Interface<'info, TokenInterface>,
+    /// Opens missing payee ATAs; deposit-only instructions leave it out
+    associated_token_program: Option<&'a Program<'info, AssociatedToken>>,
+}
+
+/// Pays out of a task's escrow PDA, in lamports or in the task's mint
+struct EscrowVault<'a, 'info> {
+    escrow_seed: Vec<u8>,
+    escrow_bump: u8,
+    escrow: &'a AccountInfo<'info>,
+    system_program: &'a Program<'info, System>,
+    /// Signer of the instruction, pays for payee ATAs it has to open
+    payer: &'a AccountInfo<'info>,
+    token: Option<TokenVault<'a, 'info>>,
+}
+
+impl<'a, 'info> EscrowVault<'a, 'info> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        task: &Account<'info, Task>,
+        escrow: &'a AccountInfo<'info>,
+        system_program: &'a Program<'info, System>,
+        payer: &'a AccountInfo<'info>,
+        mint: Option<&'a InterfaceAccount<'info, Mint>>,
+        escrow_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+        token_program: Option<&'a Interface<'info, TokenInterface>>,
+        associated_token_program: Option<&'a Program<'info, AssociatedToken>>,
+    ) -> Result<Self> {
+        let token = match task.mint {
+            Some(task_mint) => {
+                let mint = mint.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let escrow_token_account = escrow_token_account.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let token_program = token_program.ok_or(OpenfourrError::TokenAccountRequired)?;
+
+                require_keys_eq!(mint.key(), task_mint, OpenfourrError::MintMismatch);
+                require_keys_eq!(escrow_token_account.mint, task_mint, OpenfourrError::MintMismatch);
+                require_keys_eq!(escrow_token_account.owner, escrow.key(), OpenfourrError::InvalidEscrowTokenAccount);
+
+                Some(TokenVault { mint, escrow_token_account, token_program, associated_token_program })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            escrow_seed: task.escrow_seed(&task.key()),
+            escrow_bump: task.escrow_bump,
+            escrow,
+            system_program,
+            payer,
+            token,
+        })
+    }
+
+    /// Move `amount` from `payer` (or its token account for SPL tasks) into escrow
+    fn deposit(
+        &self,
+        payer: &Signer<'info>,
+        token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        amount: u64,
+    ) -> Result<()> {
+        match &self.token {
+            Some(token) => {
+                let token_account = token_account.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let balance_before = token.escrow_token_account.amount;
+
+                let cpi_context = CpiContext::new(
+                    token.token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: token_account.to_account_info(),
+                        mint: token.mint.to_account_info(),
+                        to: token.escrow_token_account.to_account_info(),
+                        authority: payer.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(cpi_context, amount, token.mint.decimals)?;
+
+                // Mints with a transfer fee would leave the escrow short of the bounty
+                let escrow_info = token.escrow_token_account.to_account_info();
+                let balance_after = TokenAccount::try_deserialize(&mut &escrow_info.try_borrow_data()?[..])?.amount;
+                require!(balance_after.saturating_sub(balance_before) >= amount, OpenfourrError::EscrowUnderfunded);
+                Ok(())
+            }
+            None => {
+                let cpi_context = CpiContext::new(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: self.escrow.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)
+            }
+        }
+    }
+
+    /// Pay `amount` to `wallet`, or to its token account for SPL tasks, opening the ATA if missing
+    fn pay(
+        &self,
+        wallet: &AccountInfo<'info>,
+        token_account: Option<&AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let escrow_seeds = &[b"escrow".as_ref(), self.escrow_seed.as_ref(), &[self.escrow_bump]];
+        let signer_seeds = &[&escrow_seeds[..]];
+
+        match &self.token {
+            Some(token) => {
+                let token_account = token_account.ok_or(OpenfourrError::TokenAccountRequired)?;
+                let associated_token_program = token.associated_token_program.ok_or(OpenfourrError::TokenAccountRequired)?;
+                open_token_account(
+                    token_account,
+                    wallet,
+                    token.mint,
+                    self.payer,
+                    self.system_program,
+                    token.token_program,
+                    associated_token_program,
+                )?;
+
+                let cpi_context = CpiContext::new_with_signer(
+                    token.token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: token.escrow_token_account.to_account_info(),
+                        mint: token.mint.to_account_info(),
+                        to: token_account.to_account_info(),
+                        authority: self.escrow.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                token_interface::transfer_checked(cpi_context, amount, token.mint.decimals)
+            }
+            None => {
+                let cpi_context = CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.escrow.to_account_info(),
+                        to: wallet.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                anchor_lang::system_program::transfer(cpi_context, amount)
+            }
+        }
+    }
+
+    /// Return `amount` to the task's funders: the client's share is paid now and the rest is
+    /// left in escrow for contributors to `claim_refund`
+    fn refund(
+        &self,
+        task: &mut Task,
+        client: &AccountInfo<'info>,
+        client_token_account: Option<&AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let client_share = if task.contributed == 0 {
+            amount
+        } else {
+            let client_funded = task.bounty_amount.checked_sub(task.contributed).ok_or(OpenfourrError::MathOverflow)?;
+            mul_div(amount, client_funded, task.bounty_amount)?
+        };
+
+        self.pay(client, client_token_account, client_share)?;
+        task.refund_pool = amount.checked_sub(client_share).ok_or(OpenfourrError::MathOverflow)?;
+
+        if task.refund_pool == 0 {
+            self.close(client, client_token_account)?;
+        }
+        Ok(())
+    }
+
+    /// Pay `gross` to the agent less the platform fee; returns `(payout, fee)`
+    fn pay_agent(
+        &self,
+        platform: &mut Platform,
+        gross: u64,
+        agent_wallet: &AccountInfo<'info>,
+        agent_token_account: Option<&AccountInfo<'info>>,
+        treasury: &AccountInfo<'info>,
+        treasury_token_account: Option<&AccountInfo<'info>>,
+    ) -> Result<(u64, u64)> {
+        let (payout, fee) = split_fee(gross, platform.fee_bps)?;
+
+        self.pay(agent_wallet, agent_token_account, payout)?;
+        self.pay(treasury, treasury_token_account, fee)?;
+
+        if self.token.is_none() {
+            platform.total_fees_collected = platform.total_fees_collected.checked_add(fee).ok_or(OpenfourrError::MathOverflow)?;
+        }
+        Ok((payout, fee))
+    }
+
+    /// Empty the drained escrow into `rent_to`: the rent reserve and rounding dust of a SOL escrow,
+    /// or any tokens left in the escrow token account (to `rent_to_token_account`) and its rent, which is closed
+    fn close(&self, rent_to: &AccountInfo<'info>, rent_to_token_account: Option<&AccountInfo<'info>>) -> Result<()> {
+        let escrow_seeds = &[b"escrow".as_ref(), self.escrow_seed.as_ref(), &[self.escrow_bump]];
+        let signer_seeds = &[&escrow_seeds[..]];
+
+        let Some(token) = &self.token else {
+            let remainder = self.escrow.lamports();
+            if remainder == 0 {
+                return Ok(());
+            }
+            let cpi_context = CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.escrow.to_account_info(),
+                    to: rent_to.to_account_info(),
+                },
+                signer_seeds,
+            );
+            return anchor_lang::system_program::transfer(cpi_context, remainder);
+        };
+
+        // Tokens sent straight to the escrow token account would block close_account
+        let escrow_info = token.escrow_token_account.to_account_info();
+        let remainder = TokenAccount::try_deserialize(&mut &escrow_info.try_borrow_data()?[..])?.amount;
+        self.pay(rent_to, rent_to_token_account, remainder)?;
+
+        let cpi_context = CpiContext::new_with_signer(
+            token.token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: token.escrow_token_account.to_account_info(),
+                destination: rent_to.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_context)
+    }
+}
+
+/// Check `token_account` is `wallet`'s account for `mint`, first opening it as `wallet`'s ATA at
+/// `payer`'s expense if it doesn't exist, so a payout never waits on the payee
+fn open_token_account<'info>(
+    token_account: &AccountInfo<'info>,
+    wallet: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+) -> Result<()> {
+    if token_account.data_is_empty() {
+        let create_context = CpiContext::new(
+            associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: payer.clone(),
+                associated_token: token_account.clone(),
+                authority: wallet.clone(),
+                mint: mint.to_account_info(),
+                system_program: system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        );
+        associated_token::create_idempotent(create_context)?;
+    }
+
+    let account = TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..])?;
+    require_keys_eq!(account.mint, mint.key(), OpenfourrError::MintMismatch);
+    require_keys_eq!(account.owner, wallet.key(), OpenfourrError::TokenAccountOwnerMismatch);
+    Ok(())
+}
+
+/// Decode a v0 account, which must still have its v0 size and carry the current account's discriminator
+fn read_legacy<T: AnchorDeserialize + Space>(info: &AccountInfo, discriminator: &[u8]) -> Result<T> {
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() == 8 + T::INIT_SPACE && data.starts_with(discriminator),
+        OpenfourrError::NotLegacyAccount
+    );
+    T::deserialize(&mut &data[8..]).map_err(|_| error!(OpenfourrError::NotLegacyAccount))
+}
+
+/// Grow a v0 account to the current layout's space, topping its rent up from `payer`, and write `account` into it
+fn write_migrated<'info, T: AccountSerialize + Space>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    account: &T,
+) -> Result<AccountMigrated> {
+    let old_space = info.data_len();
+    let new_space = 8 + T::INIT_SPACE;
+
+    let shortfall = Rent::get()?.minimum_balance(new_space).saturating_sub(info.lamports());
+    if shortfall > 0 {
+        let cpi_context = CpiContext::new(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer { from: payer.to_account_info(), to: info.clone() },
+        );
+        anchor_lang::system_program::transfer(cpi_context, shortfall)?;
+    }
+    info.resize(new_space)?;
+    account.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    Ok(AccountMigrated { account: info.key(), old_space: old_space as u64, new_space: new_space as u64 })
+}
+
+// ============ VALIDATION ============
+
+/// Whether a text field may span lines
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TextKind {
+    /// Names, titles, URLs: no control characters at all
+    Line,
+    /// Free-form prose: newlines and tabs allowed
+    Block,
+}
+
+/// Check one text field against its account space. Borsh already rejects invalid
+/// UTF-8 when decoding a `String`, so only length, control characters and blank
+/// values are left to check. Pass `empty: None` for optional fields.
+fn check_text(
+    value: &str,
+    max_len: usize,
+    kind: TextKind,
+    too_long: OpenfourrError,
+    empty: Option<OpenfourrError>,
+    invalid: OpenfourrError,
+) -> Result<()> {
+    if value.len() > max_len {
+        return Err(too_long.into());
+    }
+    if let Some(empty) = empty {
+        if value.trim().is_empty() {
+            return Err(empty.into());
+        }
+    }
+    let allowed = |c: char| kind == TextKind::Block && (c == '\n' || c == '\t');
+    if value.chars().any(|c| c.is_control() && !allowed(c)) {
+        return Err(invalid.into());
+    }
+    Ok(())
+}
+
+/// Bounds shared by `register_agent` and `update_agent`
+fn validate_agent_profile(name: &str, bio: &str, skills: &[String]) -> Result<()> {
+    check_text(
+        name,
+        MAX_NAME_LEN,
+        TextKind::Line,
+        OpenfourrError::NameTooLong,
+        Some(OpenfourrError::NameEmpty),
+        OpenfourrError::NameInvalidCharacters,
+    )?;
+    check_text(
+        bio,
+        MAX_BIO_LEN,
+        TextKind::Block,
+        OpenfourrError::BioTooLong,
+        None,
+        OpenfourrError::BioInvalidCharacters,
+    )?;
+    require!(skills.len() <= MAX_SKILLS, OpenfourrError::TooManySkills);
+    for skill in skills {
+        check_text(
+            skill,
+            MAX_SKILL_LEN,
+            TextKind::Line,
+            OpenfourrError::SkillTooLong,
+            Some(OpenfourrError::SkillEmpty),
+            OpenfourrError::SkillInvalidCharacters,
+        )?;
+    }
+    Ok(())
+}
+
+/// Bounds shared by task creation and `update_task`
+fn validate_task_details(
+    title: &str,
+    description: &str,
+    requirements: &str,
+    category: &str,
+    deadline_hours: u64,
+) -> Result<()> {
+    check_text(
+        title,
+        MAX_TITLE_LEN,
+        TextKind::Line,
+        OpenfourrError::TitleTooLong,
+        Some(OpenfourrError::TitleEmpty),
+        OpenfourrError::TitleInvalidCharacters,
+    )?;
+    check_text(
+        description,
+        MAX_DESCRIPTION_LEN,
+        TextKind::Block,
+        OpenfourrError::DescriptionTooLong,
+        Some(OpenfourrError::DescriptionEmpty),
+        OpenfourrError::DescriptionInvalidCharacters,
+    )?;
+    check_text(
+        requirements,
+        MAX_REQUIREMENTS_LEN,
+        TextKind::Block,
+        OpenfourrError::RequirementsTooLong,
+        None,
+        OpenfourrError::RequirementsInvalidCharacters,
+    )?;
+    check_text(
+        category,
+        MAX_CATEGORY_LEN,
+        TextKind::Line,
+        OpenfourrError::CategoryTooLong,
+        Some(OpenfourrError::CategoryEmpty),
+        OpenfourrError::CategoryInvalidCharacters,
+    )?;
+    require!(deadline_hours > 0 && deadline_hours <= 720, OpenfourrError::InvalidDeadline);
+    Ok(())
+}
+
+/// Bounds shared by `submit_application`, `reveal_submission` and `submit_work`
+fn validate_submission(url: &str, notes: &str) -> Result<()> {
+    check_text(
+        url,
+        MAX_URL_LEN,
+        TextKind::Line,
+        OpenfourrError::UrlTooLong,
+        Some(OpenfourrError::UrlEmpty),
+        OpenfourrError::UrlInvalidCharacters,
+    )?;
+    check_text(
+        notes,
+        MAX_NOTES_LEN,
+        TextKind::Block,
+        OpenfourrError::NotesTooLong,
+        None,
+        OpenfourrError::NotesInvalidCharacters,
+    )
+}
+
+fn validate_message(content: &str) -> Result<()> {
+    check_text(
+        content,
+        MAX_MESSAGE_LEN,
+        TextKind::Block,
+        OpenfourrError::MessageTooLong,
+        Some(OpenfourrError::MessageEmpty),
+        OpenfourrError::MessageInvalidCharacters,
+    )
+}
+
+fn validate_milestone_title(title: &str) -> Result<()> {
+    check_text(
+        title,
+        MAX_MILESTONE_TITLE_LEN,
+        TextKind::Line,
+        OpenfourrError::MilestoneTitleTooLong,
+        Some(OpenfourrError::MilestoneTitleEmpty),
+        OpenfourrError::MilestoneTitleInvalidCharacters,
+    )
+}
+
+/// Reasons given for a rejection, a judge's rejection or a dispute
+fn validate_reason(reason: &str) -> Result<()> {
+    check_text(
+        reason,
+        MAX_REASON_LEN,
+        TextKind::Block,
+        OpenfourrError::ReasonTooLong,
+        Some(OpenfourrError::ReasonEmpty),
+        OpenfourrError::ReasonInvalidCharacters,
+    )
+}
+
+fn validate_evidence_uri(evidence_uri: &str) -> Result<()> {
+    check_text(
+        evidence_uri,
+        MAX_EVIDENCE_URI_LEN,
+        TextKind::Line,
+        OpenfourrError::EvidenceUriTooLong,
+        None,
+        OpenfourrError::EvidenceUriInvalidCharacters,
+    )
+}
+
+fn validate_payout_schedule(options: &TaskOptions) -> Result<()> {
+    let schedule = &options.payout_schedule;
+    if schedule.is_empty() {
+        return Ok(());
+    }
+    // A judge verdict awards a single submission
+    require!(
+        options.mode == TaskMode::OpenCompetition && !options.use_judge,
+        OpenfourrError::InvalidTaskOptions
+    );
+    require!((2..=MAX_WINNERS).contains(&schedule.len()), OpenfourrError::InvalidPayoutSchedule);
+    require!(schedule.iter().all(|&bps| bps > 0), OpenfourrError::InvalidPayoutSchedule);
+    require!(
+        schedule.iter().map(|&bps| bps as u32).sum::<u32>() == 10000,
+        OpenfourrError::InvalidPayoutSchedule
+    );
+    Ok(())
+}
+
+fn validate_invites(invited_agents: &[Pubkey]) -> Result<()> {
+    require!(invited_agents.len() <= MAX_INVITED_AGENTS, OpenfourrError::TooManyInvites);
+    for (i, agent) in invited_agents.iter().enumerate() {
+        require!(!invited_agents[..i].contains(agent), OpenfourrError::DuplicateInvite);
+    }
+    Ok(())
+}
+
+// ============ CONTEXTS ============
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = authority, space = 8 + Platform::INIT_SPACE, seeds = [b"platform"], bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA, holds lamports only
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdatePlatform<'info> {
+    #[account(mut, seeds = [b"platform"], bump = platform.bump, has_one = authority)]
+    pub platform: Account<'info, Platform>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(seeds = [b"platform"], bump = platform.bump, has_one = authority)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub treasury: AccountInfo<'info>,
+    /// CHECK: Any wallet chosen by the authority
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL fees only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Destination's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub destination_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    pub new_authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RegisterAgent<'info> {
+    #[account(init, payer = owner, space = 8 + AgentProfile::INIT_SPACE, seeds = [b"agent", owner.key().as_ref()], bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateAgent<'info> {
+    #[account(mut, seeds = [b"agent", owner.key().as_ref()], bump = agent_profile.bump, has_one = owner)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    pub owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut, seeds = [b"agent", owner.key().as_ref()], bump = agent_profile.bump, has_one = owner)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    /// CHECK: Stake vault PDA, holds lamports only
+    #[account(mut, seeds = [b"stake", owner.key().as_ref()], bump)]
+    pub stake_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseAgent<'info> {
+    #[account(mut, close = owner, seeds = [b"agent", owner.key().as_ref()], bump = agent_profile.bump, has_one = owner)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    /// CHECK: Stake vault PDA, holds lamports only
+    #[account(mut, seeds = [b"stake", owner.key().as_ref()], bump)]
+    pub stake_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateTask<'info> {
+    #[account(init, payer = client, space = 8 + Task::INIT_SPACE, seeds = [b"task", platform.total_tasks.to_le_bytes().as_ref()], bump)]
+    pub task: Account<'info, Task>,
+    #[account(init_if_needed, payer = client, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", client.key().as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Escrow PDA
+    #[account(mut, seeds = [b"escrow", platform.total_tasks.to_le_bytes().as_ref()], bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub client_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Escrow ATA, created in handler
+    #[account(mut)]
+    pub escrow_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateClientTask<'info> {
+    #[account(init_if_needed, payer = client, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", client.key().as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(init, payer = client, space = 8 + Task::INIT_SPACE, seeds = [b"task", client.key().as_ref(), client_profile.task_nonce.to_le_bytes().as_ref()], bump)]
+    pub task: Account<'info, Task>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Escrow PDA
+    #[account(mut, seeds = [b"escrow", task.key().as_ref()], bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub client_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Escrow ATA, created in handler
+    #[account(mut)]
+    pub escrow_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitApplication<'info> {
+    #[account(init, payer = agent_owner, space = 8 + Submission::INIT_SPACE, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, seeds = [b"agent", agent_owner.key().as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub agent_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevealSubmission<'info> {
+    #[account(mut, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump = submission.bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    pub agent_owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SendMessage<'info> {
+    #[account(init, payer = sender, space = 8 + Message::INIT_SPACE, seeds = [b"message", task.key().as_ref(), task.message_count.to_le_bytes().as_ref()], bump)]
+    pub message: Account<'info, Message>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    pub submission: Option<Account<'info, Submission>>,
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AddMilestones<'info> {
+    #[account(init, payer = client, space = 8 + MilestonePlan::INIT_SPACE, seeds = [b"milestones", task.key().as_ref()], bump)]
+    pub milestone_plan: Account<'info, MilestonePlan>,
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, seeds = [b"milestones", task.key().as_ref()], bump = milestone_plan.bump)]
+    pub milestone_plan: Account<'info, MilestonePlan>,
+    #[account(mut, constraint = submission.task == task.key())]
+    pub submission: Account<'info, Submission>,
+    #[account(mut, seeds = [b"agent", submission.agent.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    /// CHECK: Agent wallet
+    #[account(mut, constraint = agent_wallet.key() == submission.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub platform_treasury: AccountInfo<'info>,
+    /// CHECK: Client wallet, receives escrow ATA rent
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    #[account(init_if_needed, payer = releaser, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", task.client.as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut)]
+    pub releaser: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Agent's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub agent_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SelectWinner<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    #[account(mut, constraint = submission.task == task.key())]
+    pub submission: Account<'info, Submission>,
+    #[account(mut, seeds = [b"agent", submission.agent.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    /// CHECK: Agent wallet
+    #[account(mut, constraint = agent_wallet.key() == submission.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub platform_treasury: AccountInfo<'info>,
+    #[account(init_if_needed, payer = client, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", client.key().as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Agent's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub agent_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SelectWinners<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub platform_treasury: AccountInfo<'info>,
+    #[account(init_if_needed, payer = client, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", client.key().as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Treasury's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RateClient<'info> {
+    pub task: Account<'info, Task>,
+    #[account(mut, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump = submission.bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(init_if_needed, payer = agent_owner, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", task.client.as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut)]
+    pub agent_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimTask<'info> {
+    #[account(init, payer = agent_owner, space = 8 + Submission::INIT_SPACE, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, seeds = [b"agent", agent_owner.key().as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub agent_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitBid<'info> {
+    #[account(init, payer = agent_owner, space = 8 + Bid::INIT_SPACE, seeds = [b"bid", task.key().as_ref(), agent_owner.key().as_ref()], bump)]
+    pub bid: Account<'info, Bid>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(seeds = [b"agent", agent_owner.key().as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub agent_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawBid<'info> {
+    #[account(mut, close = agent_owner, seeds = [b"bid", task.key().as_ref(), agent_owner.key().as_ref()], bump = bid.bump)]
+    pub bid: Account<'info, Bid>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut)]
+    pub agent_owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseBid<'info> {
+    #[account(mut, close = agent_wallet, constraint = bid.task == task.key(), seeds = [b"bid", task.key().as_ref(), bid.agent.as_ref()], bump = bid.bump)]
+    pub bid: Account<'info, Bid>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    /// CHECK: Bidder's wallet, receives rent
+    #[account(mut, constraint = agent_wallet.key() == bid.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptBid<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    #[account(mut, close = agent_wallet, seeds = [b"bid", task.key().as_ref(), bid.agent.as_ref()], bump = bid.bump)]
+    pub bid: Account<'info, Bid>,
+    /// CHECK: Bidder's wallet, receives the bid's rent
+    #[account(mut, constraint = agent_wallet.key() == bid.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitWork<'info> {
+    #[account(mut, seeds = [b"submission", task.key().as_ref(), agent_owner.key().as_ref()], bump = submission.bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    pub agent_owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RejectWork<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    #[account(mut, seeds = [b"submission", task.key().as_ref(), agent_profile.owner.as_ref()], bump = submission.bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut, seeds = [b"agent", agent_profile.owner.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    pub client: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelTask<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    #[account(init_if_needed, payer = client, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", client.key().as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    /// Milestone tasks only
+    #[account(seeds = [b"milestones", task.key().as_ref()], bump = milestone_plan.bump)]
+    pub milestone_plan: Option<Account<'info, MilestonePlan>>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestExtension<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(constraint = submission.task == task.key() && submission.agent == agent_owner.key())]
+    pub submission: Account<'info, Submission>,
+    pub agent_owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApproveExtension<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    pub client: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateTask<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    pub client: Signer<'info>,
+    /// Milestone tasks only
+    #[account(seeds = [b"milestones", task.key().as_ref()], bump = milestone_plan.bump)]
+    pub milestone_plan: Option<Account<'info, MilestonePlan>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TopUpBounty<'info> {
+    #[account(mut, constraint = task.client == client.key())]
+    pub task: Account<'info, Task>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub client: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub client_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Contribute<'info> {
+    #[account(init_if_needed, payer = funder, space = 8 + Contribution::INIT_SPACE, seeds = [b"contribution", task.key().as_ref(), funder.key().as_ref()], bump)]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub funder_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VoteWinner<'info> {
+    #[account(mut, seeds = [b"contribution", task.key().as_ref(), funder.key().as_ref()], bump = contribution.bump)]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, constraint = submission.task == task.key())]
+    pub submission: Account<'info, Submission>,
+    pub funder: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, close = funder, seeds = [b"contribution", task.key().as_ref(), contribution.funder.as_ref()], bump = contribution.bump)]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    /// CHECK: Funder wallet, receives the refund and the contribution's rent
+    #[account(mut, constraint = funder.key() == contribution.funder)]
+    pub funder: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Client wallet, receives the escrow token account's rent
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Funder's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub funder_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DeclineInvite<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    pub agent_owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RaiseDispute<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(init, payer = raiser, space = 8 + Dispute::INIT_SPACE, seeds = [b"dispute", task.key().as_ref()], bump)]
+    pub dispute: Account<'info, Dispute>,
+    /// Raiser's submission; required unless the client is raising
+    pub submission: Option<Account<'info, Submission>>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub raiser: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AutoRefundExpired<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(init_if_needed, payer = payer, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", task.client.as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    /// CHECK: Client wallet
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    /// Any wallet; pays for the client profile of tasks posted before profiles existed
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct JudgeVerdict<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, constraint = submission.task == task.key())]
+    pub submission: Account<'info, Submission>,
+    #[account(mut, seeds = [b"agent", submission.agent.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Agent stake vault, verified in handler
+    #[account(mut)]
+    pub stake_vault: AccountInfo<'info>,
+    /// CHECK: Client wallet, receives slashed stake
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    pub judge: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeVerdict<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, constraint = submission.task == task.key())]
+    pub submission: Account<'info, Submission>,
+    #[account(mut, seeds = [b"agent", submission.agent.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    /// CHECK: Agent wallet
+    #[account(mut, constraint = agent_wallet.key() == submission.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub platform_treasury: AccountInfo<'info>,
+    /// CHECK: Client wallet, receives escrow ATA rent
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    #[account(init_if_needed, payer = payer, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", task.client.as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    /// Any wallet; pays for the client profile of tasks posted before profiles existed
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Agent's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub agent_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, close = raiser, seeds = [b"dispute", task.key().as_ref()], bump = dispute.bump)]
+    pub dispute: Account<'info, Dispute>,
+    /// CHECK: Dispute raiser, receives the dispute rent and any returned bond
+    #[account(mut, constraint = raiser.key() == dispute.raised_by)]
+    pub raiser: AccountInfo<'info>,
+    #[account(mut)]
+    pub submission: Option<Account<'info, Submission>>,
+    #[account(mut)]
+    pub agent_profile: Option<Account<'info, AgentProfile>>,
+    /// CHECK: Agent wallet, checked against submission in handler
+    #[account(mut)]
+    pub agent_wallet: Option<AccountInfo<'info>>,
+    /// CHECK: Agent stake vault, verified in handler; only needed when slashing
+    #[account(mut)]
+    pub stake_vault: Option<AccountInfo<'info>>,
+    /// CHECK: Escrow
+    #[account(mut, seeds = [b"escrow", task.escrow_seed(&task.key()).as_ref()], bump = task.escrow_bump)]
+    pub escrow: AccountInfo<'info>,
+    /// CHECK: Client wallet
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    #[account(init_if_needed, payer = arbiter, space = 8 + ClientProfile::INIT_SPACE, seeds = [b"client", task.client.as_ref()], bump)]
+    pub client_profile: Account<'info, ClientProfile>,
+    #[account(mut, seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+    /// CHECK: Treasury PDA
+    #[account(mut, seeds = [b"treasury"], bump = platform.treasury_bump)]
+    pub platform_treasury: AccountInfo<'info>,
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // SPL bounties only
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Agent's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub agent_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Client's token account; opened as its ATA in the handler if missing
+    #[account(mut)]
+    pub client_token_account: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseSubmission<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, close = agent_wallet, constraint = submission.task == task.key(), seeds = [b"submission", task.key().as_ref(), submission.agent.as_ref()], bump = submission.bump)]
+    pub submission: Account<'info, Submission>,
+    #[account(mut)]
+    pub agent_profile: Option<Account<'info, AgentProfile>>,
+    /// CHECK: Agent wallet, receives rent
+    #[account(mut, constraint = agent_wallet.key() == submission.agent)]
+    pub agent_wallet: AccountInfo<'info>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeSubmissions<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseMessage<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(mut, close = sender, constraint = message.task == task.key(), seeds = [b"message", task.key().as_ref(), message.message_id.to_le_bytes().as_ref()], bump = message.bump)]
+    pub message: Account<'info, Message>,
+    /// CHECK: Message sender, receives rent
+    #[account(mut, constraint = sender.key() == message.sender)]
+    pub sender: AccountInfo<'info>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseMessages<'info> {
+    #[account(mut)]
+    pub task: Account<'info, Task>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseTask<'info> {
+    #[account(mut, close = client)]
+    pub task: Account<'info, Task>,
+    /// CHECK: Client wallet, receives rent
+    #[account(mut, constraint = client.key() == task.client)]
+    pub client: AccountInfo<'info>,
+    #[account(mut)]
+    pub milestone_plan: Option<Account<'info, MilestonePlan>>,
+    #[account(seeds = [b"platform"], bump = platform.bump)]
+    pub platform: Account<'info, Platform>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigratePlatform<'info> {
+    /// CHECK: v0 platform, decoded and rewritten in the handler
+    #[account(mut, owner = crate::ID, seeds = [b"platform"], bump)]
+    pub platform: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateTask<'info> {
+    /// CHECK: v0 task, decoded and rewritten in the handler
+    #[account(mut, owner = crate::ID)]
+    pub task: AccountInfo<'info>,
+    /// CHECK: Escrow, checked against the task's id and escrow bump in the handler
+    #[account(mut)]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateAgent<'info> {
+    /// CHECK: v0 agent profile, decoded and rewritten in the handler
+    #[account(mut, owner = crate::ID)]
+    pub agent_profile: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateSubmission<'info> {
+    /// CHECK: v0 submission, decoded and rewritten in the handler
+    #[account(mut, owner = crate::ID)]
+    pub submission: AccountInfo<'info>,
+    /// Migrated task the submission belongs to
+    pub task: Account<'info, Task>,
+    /// Migrated profile of the submitting agent
+    #[account(mut, seeds = [b"agent", agent_profile.owner.as_ref()], bump = agent_profile.bump)]
+    pub agent_profile: Account<'info, AgentProfile>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateMessage<'info> {
+    /// CHECK: v0 message, decoded and rewritten in the handler
+    #[account(mut, owner = crate::ID)]
+    pub message: AccountInfo<'info>,
+    /// Migrated task the message belongs to
+    pub task: Account<'info, Task>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// ============ STATE ============
+
+#[account]
+#[derive(InitSpace)]
+pub struct Platform {
+    pub authority: Pubkey,
+    pub fee_bps: u16,
+    pub total_tasks: u64,
+    pub total_completed: u64,
+    /// Lamports paid out through SOL tasks
+    pub total_volume: u64,
+    pub bump: u8,
+    pub arbiter: Option<Pubkey>,
+    pub judge: Option<Pubkey>,
+    pub pending_authority: Option<Pubkey>,
+    /// Blocks task creation, submissions and payouts; refunds still work
+    pub paused: bool,
+    pub treasury_bump: u8,
+    /// Lamport fees paid into the treasury PDA from SOL tasks
+    pub total_fees_collected: u64,
+    /// Seconds a finished task's accounts are kept before they can be closed
+    pub retention_period: i64,
+    /// Share of an agent's stake slashed on judge rejections and disputes
+    pub slash_bps: u16,
+    /// Lamports locked by `raise_dispute`
+    pub dispute_bond: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AgentProfile {
+    pub owner: Pubkey,
+    #[max_len(MAX_NAME_LEN)]
+    pub name: String,
+    #[max_len(MAX_BIO_LEN)]
+    pub bio: String,
+    #[max_len(MAX_SKILLS, MAX_SKILL_LEN)]
+    pub skills: Vec<String>,
+    pub hourly_rate: u64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    /// Lamports earned from SOL tasks
+    pub total_earned: u64,
+    pub rating_sum: u64,
+    pub rating_count: u64,
+    pub registered_at: i64,
+    pub is_active: bool,
+    pub bump: u8,
+    /// Submissions still `Pending`; the profile can't be closed until this is 0
+    pub open_submissions: u64,
+    /// Lamports bonded in the `[b"stake", owner]` vault
+    pub staked: u64,
+    /// Stake waiting out `UNSTAKE_COOLDOWN`; still slashable
+    pub unstaking: u64,
+    pub unstake_available_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClientProfile {
+    pub owner: Pubkey,
+    /// Next task id for `create_client_task`, part of the task PDA seeds
+    pub task_nonce: u64,
+    pub tasks_posted: u64,
+    pub tasks_completed: u64,
+    pub tasks_cancelled: u64,
+    /// Tasks refunded by `auto_refund_expired` without a winner
+    pub tasks_expired: u64,
+    /// Lamports paid out of the client's SOL tasks
+    pub total_paid: u64,
+    pub rating_sum: u64,
+    pub rating_count: u64,
+    pub bump: u8,
+}
+
+impl ClientProfile {
+    /// Fill in a profile that `init_if_needed` just created for a task posted before client profiles existed
+    fn adopt(&mut self, client: Pubkey) {
+        if self.owner == Pubkey::default() {
+            self.owner = client;
+            self.bump = Pubkey::find_program_address(&[b"client", client.as_ref()], &crate::ID).1;
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Task {
+    /// `platform.total_tasks` at creation, or the client's `task_nonce` for `create_client_task`
+    pub id: u64,
+    pub client: Pubkey,
+    #[max_len(MAX_TITLE_LEN)]
+    pub title: String,
+    #[max_len(MAX_DESCRIPTION_LEN)]
+    pub description: String,
+    #[max_len(MAX_REQUIREMENTS_LEN)]
+    pub requirements: String,
+    #[max_len(MAX_CATEGORY_LEN)]
+    pub category: String,
+    pub bounty_amount: u64,
+    pub created_at: i64,
+    pub deadline: i64,
+    pub status: TaskStatus,
+    pub submission_count: u64,
+    pub message_count: u64,
+    pub extension_requested: bool,
+    pub extension_hours: u64,
+    pub dispute_raised_by: Option<Pubkey>,
+    pub winning_submission: Option<Pubkey>,
+    /// Set when the task reaches `Completed` or `Cancelled`
+    pub completed_at: Option<i64>,
+    pub bump: u8,
+    pub escrow_bump: u8,
+    /// SPL mint of the bounty; `None` for native SOL
+    pub mint: Option<Pubkey>,
+    pub mode: TaskMode,
+    pub assigned_agent: Option<Pubkey>,
+    pub rejection_count: u8,
+    /// Judge snapshotted from `Platform` when the client opted in
+    pub judge: Option<Pubkey>,
+    pub verdict: Option<Verdict>,
+    pub commit_reveal: bool,
+    /// Number of milestones in the task's `MilestonePlan`; 0 for single payout
+    pub milestone_count: u8,
+    pub released_amount: u64,
+    pub closed_submissions: u64,
+    pub closed_messages: u64,
+    /// Only `invited_agents` may apply, claim or bid; stays set after every invitee declines
+    pub invite_only: bool,
+    #[max_len(MAX_INVITED_AGENTS)]
+    pub invited_agents: Vec<Pubkey>,
+    /// Other wallets can `contribute` to the escrow
+    pub crowdfunded: bool,
+    /// Escrow funded by contributors; the rest of `bounty_amount` came from the client
+    pub contributed: u64,
+    pub contributor_count: u64,
+    /// Submission that took a majority of the contributed funds; see `binding_vote`
+    pub vote_leader: Option<Pubkey>,
+    /// Funder votes behind `vote_leader` when it took the lead
+    pub vote_leader_votes: u64,
+    /// Refund left in escrow for contributors once the task is cancelled or split
+    pub refund_pool: u64,
+    pub refunds_paid: u64,
+    pub refunds_claimed: u64,
+    /// Stake an agent needs to apply, claim or bid
+    pub min_stake: u64,
+    /// Basis points paid to each place by `select_winners`; empty for a single winner
+    #[max_len(MAX_WINNERS)]
+    pub payout_schedule: Vec<u16>,
+    /// Opened by `create_client_task`; its escrow is keyed by the task address instead of the id
+    pub per_client: bool,
+    /// Pending bids; `close_task` waits until they are withdrawn
+    pub bid_count: u64,
+    /// Sealed submissions revealed so far; the rest are forfeited once the reveal window closes
+    pub revealed_count: u64,
+}
+
+impl Task {
+    /// Escrow PDA seed: the task id for `create_task` tasks, the task address for per-client ones
+    pub fn escrow_seed(&self, task: &Pubkey) -> Vec<u8> {
+        if self.per_client {
+            task.to_bytes().to_vec()
+        } else {
+            self.id.to_le_bytes().to_vec()
+        }
+    }
+
+    pub fn is_invited(&self, agent: &Pubkey) -> bool {
+        !self.invite_only || self.invited_agents.contains(agent)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.status, TaskStatus::Completed | TaskStatus::Cancelled)
+    }
+
+    /// `vote_leader`, as long as its votes are still a majority of the contributed funds after later contributions
+    pub fn binding_vote(&self) -> Option<Pubkey> {
+        self.vote_leader.filter(|_| (self.vote_leader_votes as u128) * 2 > self.contributed as u128)
+    }
+
+    /// Whether a commit-reveal task's reveal window has passed, forfeiting unrevealed submissions
+    pub fn reveal_window_closed(&self, now: i64) -> Result<bool> {
+        Ok(now >= self.deadline.checked_add(REVEAL_WINDOW).ok_or(OpenfourrError::MathOverflow)?)
+    }
+
+    /// Bounty still held in escrow
+    pub fn remaining_bounty(&self) -> Result<u64> {
+        Ok(self.bounty_amount.checked_sub(self.released_amount).ok_or(OpenfourrError::MathOverflow)?)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MilestonePlan {
+    pub task: Pubkey,
+    #[max_len(MAX_MILESTONES)]
+    pub milestones: Vec<Milestone>,
+    pub released_count: u8,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Submission {
+    pub task_id: u64,
+    pub task: Pubkey,
+    pub agent: Pubkey,
+    #[max_len(MAX_URL_LEN)]
+    pub submission_url: String,
+    #[max_len(MAX_NOTES_LEN)]
+    pub submission_notes: String,
+    pub submitted_at: i64,
+    pub status: SubmissionStatus,
+    pub bump: u8,
+    /// Hash of the sealed work on commit-reveal tasks
+    pub commitment: Option<[u8; 32]>,
+    pub revealed_at: Option<i64>,
+    /// Contributed amount backing this submission on crowdfunded tasks
+    pub funder_votes: u64,
+    /// Rating the winning agent gave the client
+    pub client_rating: Option<u8>,
+}
+
+impl Submission {
+    /// Sealed submissions can't be picked until their preimage is revealed
+    pub fn is_revealed(&self) -> bool {
+        self.commitment.is_none() || self.revealed_at.is_some()
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bid {
+    pub task: Pubkey,
+    pub agent: Pubkey,
+    pub price: u64,
+    pub eta_hours: u64,
+    pub submitted_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub task: Pubkey,
+    pub raised_by: Pubkey,
+    #[max_len(MAX_REASON_LEN)]
+    pub reason: String,
+    #[max_len(MAX_EVIDENCE_URI_LEN)]
+    pub evidence_uri: String,
+    /// Lamports locked on top of rent; forfeited to the treasury unless the arbiter returns it
+    pub bond: u64,
+    pub raised_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Contribution {
+    pub task: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    /// Submission this funder voted for
+    pub vote: Option<Pubkey>,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Message {
+    pub task_id: u64,
+    pub task: Pubkey,
+    pub message_id: u64,
+    pub sender: Pubkey,
+    #[max_len(MAX_MESSAGE_LEN)]
+    pub content: String,
+    pub sent_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum TaskStatus { Open, InProgress, PendingReview, Completed, Rejected, Cancelled, Disputed }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SubmissionStatus { Pending, Selected, NotSelected }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum TaskMode { OpenCompetition, ExclusiveClaim, Bidding }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SlashTarget { Client, Treasury }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum DisputeResolution { AwardSubmission, RefundClient, Split { agent_bps: u16 } }
 
-#[derive(Accounts)]
-pub struct RequestExtension<'info> {
-    #[account(mut)]
-    pub task: Account<'info, Task>,
-    #[account(constraint = submission.task_id == task.id && submission.agent == agent_owner.key())]
-    pub submission: Account<'info, Submission>,
-    pub agent_owner: Signer<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Verdict {
+    pub submission: Pubkey,
+    pub rating: u8,
+    pub issued_at: i64,
 }
 
-#[derive(Accounts)]
-pub struct ApproveExtension<'info> {
-    #[account(mut, constraint = task.client == client.key())]
-    pub task: Account<'info, Task>,
-    pub client: Signer<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Milestone {
+    #[max_len(MAX_MILESTONE_TITLE_LEN)]
+    pub title: String,
+    pub amount: u64,
+    pub deadline: i64,
+    pub released_at: Option<i64>,
 }
 
-#[derive(Accounts)]
-pub struct RaiseDispute<'info> {
-    #[account(mut)]
-    pub task: Account<'info, Task>,
-    pub raiser: Signer<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct MilestoneInput {
+    pub title: String,
+    pub amount: u64,
+    pub deadline_hours: u64,
 }
 
-#[derive(Accounts)]
-pub struct AutoRefundExpired<'info> {
-    #[account(mut)]
-    pub task: Account<'info, Task>,
-    /// CHECK: Escrow
-    #[account(mut, seeds = [b"escrow", task.id.to_le_bytes().as_ref()], bump = task.escrow_bump)]
-    pub escrow: AccountInfo<'info>,
-    /// CHECK: Client wallet
-    #[account(mut, constraint = client.key() == task.client)]
-    pub client: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct TaskOptions {
+    pub mode: TaskMode,
+    /// Let the platform judge approve submissions and release escrow
+    pub use_judge: bool,
+    /// Agents commit a hash before the deadline and reveal after it
+    pub commit_reveal: bool,
+    /// Restrict the task to these agent owners; empty means open to all
+    pub invited_agents: Vec<Pubkey>,
+    /// Shortcut for inviting exactly one agent
+    pub direct_hire: Option<Pubkey>,
+    /// Let other wallets add to the escrow and vote on the winner
+    pub crowdfunded: bool,
+    /// Lamports an agent must have staked to take part
+    pub min_stake: u64,
+    /// Basis points per place, best first, summing to 10000; empty for a single winner
+    pub payout_schedule: Vec<u16>,
 }
 
-// ============ STATE ============
+// Layouts of the first devnet deployment, read only by the `migrate_*` instructions. They are frozen,
+// so lengths are spelled out rather than taken from the `MAX_*` constants.
 
-#[account]
-#[derive(InitSpace)]
-pub struct Platform {
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct PlatformV0 {
     pub authority: Pubkey,
     pub fee_bps: u16,
     pub total_tasks: u64,
@@ -460,30 +4271,30 @@ pub struct Platform {
     pub bump: u8,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct AgentProfile {
-    pub owner: Pubkey,
-    #[max_len(32)]
-    pub name: String,
-    #[max_len(500)]
-    pub bio: String,
-    #[max_len(10, 32)]
-    pub skills: Vec<String>,
-    pub hourly_rate: u64,
-    pub tasks_completed: u64,
-    pub tasks_failed: u64,
-    pub total_earned: u64,
-    pub rating_sum: u64,
-    pub rating_count: u64,
-    pub registered_at: i64,
-    pub is_active: bool,
-    pub bump: u8,
+impl PlatformV0 {
+    fn migrate(self) -> Platform {
+        Platform {
+            authority: self.authority,
+            fee_bps: self.fee_bps,
+            total_tasks: self.total_tasks,
+            total_completed: self.total_completed,
+            total_volume: self.total_volume,
+            bump: self.bump,
+            arbiter: None,
+            judge: None,
+            pending_authority: None,
+            paused: false,
+            treasury_bump: Pubkey::find_program_address(&[b"treasury"], &crate::ID).1,
+            total_fees_collected: 0,
+            retention_period: 0,
+            slash_bps: 0,
+            dispute_bond: DEFAULT_DISPUTE_BOND,
+        }
+    }
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Task {
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct TaskV0 {
     pub id: u64,
     pub client: Pubkey,
     #[max_len(100)]
@@ -509,9 +4320,105 @@ pub struct Task {
     pub escrow_bump: u8,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Submission {
+impl TaskV0 {
+    /// v0 tasks were all SOL-funded open competitions escrowed under their id
+    fn migrate(self) -> Task {
+        Task {
+            id: self.id,
+            client: self.client,
+            title: self.title,
+            description: self.description,
+            requirements: self.requirements,
+            category: self.category,
+            bounty_amount: self.bounty_amount,
+            created_at: self.created_at,
+            deadline: self.deadline,
+            status: self.status,
+            submission_count: self.submission_count,
+            message_count: self.message_count,
+            extension_requested: self.extension_requested,
+            extension_hours: self.extension_hours,
+            dispute_raised_by: self.dispute_raised_by,
+            winning_submission: self.winning_submission,
+            completed_at: self.completed_at,
+            bump: self.bump,
+            escrow_bump: self.escrow_bump,
+            mint: None,
+            mode: TaskMode::OpenCompetition,
+            assigned_agent: None,
+            rejection_count: 0,
+            judge: None,
+            verdict: None,
+            commit_reveal: false,
+            milestone_count: 0,
+            released_amount: 0,
+            closed_submissions: 0,
+            closed_messages: 0,
+            invite_only: false,
+            invited_agents: vec![],
+            crowdfunded: false,
+            contributed: 0,
+            contributor_count: 0,
+            vote_leader: None,
+            vote_leader_votes: 0,
+            refund_pool: 0,
+            refunds_paid: 0,
+            refunds_claimed: 0,
+            min_stake: 0,
+            payout_schedule: vec![],
+            per_client: false,
+            bid_count: 0,
+            revealed_count: 0,
+        }
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct AgentProfileV0 {
+    pub owner: Pubkey,
+    #[max_len(32)]
+    pub name: String,
+    #[max_len(500)]
+    pub bio: String,
+    #[max_len(10, 32)]
+    pub skills: Vec<String>,
+    pub hourly_rate: u64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    pub total_earned: u64,
+    pub rating_sum: u64,
+    pub rating_count: u64,
+    pub registered_at: i64,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl AgentProfileV0 {
+    fn migrate(self) -> AgentProfile {
+        AgentProfile {
+            owner: self.owner,
+            name: self.name,
+            bio: self.bio,
+            skills: self.skills,
+            hourly_rate: self.hourly_rate,
+            tasks_completed: self.tasks_completed,
+            tasks_failed: self.tasks_failed,
+            total_earned: self.total_earned,
+            rating_sum: self.rating_sum,
+            rating_count: self.rating_count,
+            registered_at: self.registered_at,
+            is_active: self.is_active,
+            bump: self.bump,
+            open_submissions: 0,
+            staked: 0,
+            unstaking: 0,
+            unstake_available_at: 0,
+        }
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct SubmissionV0 {
     pub task_id: u64,
     pub agent: Pubkey,
     #[max_len(500)]
@@ -523,9 +4430,27 @@ pub struct Submission {
     pub bump: u8,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Message {
+impl SubmissionV0 {
+    fn migrate(self, task: Pubkey) -> Submission {
+        Submission {
+            task_id: self.task_id,
+            task,
+            agent: self.agent,
+            submission_url: self.submission_url,
+            submission_notes: self.submission_notes,
+            submitted_at: self.submitted_at,
+            status: self.status,
+            bump: self.bump,
+            commitment: None,
+            revealed_at: None,
+            funder_votes: 0,
+            client_rating: None,
+        }
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct MessageV0 {
     pub task_id: u64,
     pub message_id: u64,
     pub sender: Pubkey,
@@ -535,30 +4460,283 @@ pub struct Message {
     pub bump: u8,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
-pub enum TaskStatus { Open, InProgress, PendingReview, Completed, Rejected, Cancelled, Disputed }
-
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
-pub enum SubmissionStatus { Pending, Selected, NotSelected }
+impl MessageV0 {
+    fn migrate(self, task: Pubkey) -> Message {
+        Message {
+            task_id: self.task_id,
+            task,
+            message_id: self.message_id,
+            sender: self.sender,
+            content: self.content,
+            sent_at: self.sent_at,
+            bump: self.bump,
+        }
+    }
+}
 
 // ============ EVENTS ============
 
 #[event]
-pub struct TaskCreated { pub task_id: u64, pub client: Pubkey, pub title: String, pub bounty: u64, pub deadline: i64 }
+pub struct PlatformInitialized { pub authority: Pubkey, pub fee_bps: u16, pub dispute_bond: u64 }
+#[event]
+pub struct AgentRegistered {
+    pub agent: Pubkey,
+    pub profile: Pubkey,
+    pub name: String,
+    pub bio: String,
+    pub skills: Vec<String>,
+    pub hourly_rate: u64,
+    pub registered_at: i64,
+}
+#[event]
+pub struct AgentUpdated { pub agent: Pubkey, pub name: String, pub bio: String, pub skills: Vec<String>, pub hourly_rate: u64 }
+#[event]
+pub struct AgentStatusChanged { pub agent: Pubkey, pub is_active: bool }
+#[event]
+pub struct AgentClosed { pub agent: Pubkey }
+#[event]
+pub struct TaskCreated {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub client: Pubkey,
+    pub escrow: Pubkey,
+    pub title: String,
+    pub description: String,
+    pub requirements: String,
+    pub category: String,
+    pub bounty: u64,
+    pub mint: Option<Pubkey>,
+    pub mode: TaskMode,
+    pub judge: Option<Pubkey>,
+    pub commit_reveal: bool,
+    pub crowdfunded: bool,
+    pub invited_agents: Vec<Pubkey>,
+    pub min_stake: u64,
+    pub payout_schedule: Vec<u16>,
+    pub created_at: i64,
+    pub deadline: i64,
+}
+#[event]
+pub struct SubmissionCreated {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub submission_url: String,
+    pub submission_notes: String,
+    pub submitted_at: i64,
+}
+#[event]
+pub struct MessageSent {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub message: Pubkey,
+    pub message_id: u64,
+    pub sender: Pubkey,
+    pub content: String,
+    pub sent_at: i64,
+}
+#[event]
+pub struct WinnerSelected { pub task: Pubkey, pub task_id: u64, pub submission: Pubkey, pub agent: Pubkey, pub payout: u64, pub rating: u8 }
+#[event]
+pub struct UnfilledPlacesRefunded { pub task: Pubkey, pub task_id: u64, pub client: Pubkey, pub refund: u64, pub refund_pool: u64 }
+#[event]
+pub struct TaskCancelled { pub task: Pubkey, pub task_id: u64, pub refund: u64 }
+#[event]
+pub struct ExtensionRequested { pub task: Pubkey, pub task_id: u64, pub agent: Pubkey, pub extra_hours: u64 }
+#[event]
+pub struct ExtensionApproved { pub task: Pubkey, pub task_id: u64, pub new_deadline: i64 }
+#[event]
+pub struct ExtensionDenied { pub task: Pubkey, pub task_id: u64 }
+#[event]
+pub struct DisputeRaised {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub dispute: Pubkey,
+    pub raised_by: Pubkey,
+    pub reason: String,
+    pub evidence_uri: String,
+    pub bond: u64,
+    pub raised_at: i64,
+}
+#[event]
+pub struct AutoRefunded { pub task: Pubkey, pub task_id: u64, pub refund: u64 }
+#[event]
+pub struct SubmissionCommitted {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub commitment: [u8; 32],
+    pub committed_at: i64,
+}
+#[event]
+pub struct SubmissionRevealed {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub submission_url: String,
+    pub submission_notes: String,
+    pub revealed_at: i64,
+}
+#[event]
+pub struct MilestonesAdded {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub milestone_plan: Pubkey,
+    pub milestones: Vec<Milestone>,
+    pub total: u64,
+}
+#[event]
+pub struct MilestoneReleased {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub index: u8,
+    pub agent: Pubkey,
+    pub amount: u64,
+    pub payout: u64,
+    pub released_at: i64,
+}
+#[event]
+pub struct BidSubmitted {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub bid: Pubkey,
+    pub agent: Pubkey,
+    pub price: u64,
+    pub eta_hours: u64,
+    pub submitted_at: i64,
+}
+#[event]
+pub struct BidWithdrawn { pub task: Pubkey, pub task_id: u64, pub bid: Pubkey, pub agent: Pubkey }
+#[event]
+pub struct BidClosed { pub task: Pubkey, pub task_id: u64, pub bid: Pubkey, pub agent: Pubkey }
+#[event]
+pub struct BidAccepted { pub task: Pubkey, pub task_id: u64, pub bid: Pubkey, pub agent: Pubkey, pub price: u64, pub refund: u64 }
+#[event]
+pub struct TaskUpdated {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub title: String,
+    pub description: String,
+    pub requirements: String,
+    pub category: String,
+    pub deadline: i64,
+}
+#[event]
+pub struct BountyIncreased { pub task: Pubkey, pub task_id: u64, pub amount: u64, pub new_bounty: u64 }
+#[event]
+pub struct ClientRated { pub task: Pubkey, pub task_id: u64, pub client: Pubkey, pub agent: Pubkey, pub rating: u8 }
+#[event]
+pub struct ContributionAdded {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub contribution: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub total_contributed: u64,
+    pub new_bounty: u64,
+}
+#[event]
+pub struct FunderVoted {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub funder: Pubkey,
+    pub submission: Pubkey,
+    pub weight: u64,
+    pub total_votes: u64,
+    pub vote_leader: Option<Pubkey>,
+}
+#[event]
+pub struct RefundClaimed { pub task: Pubkey, pub task_id: u64, pub funder: Pubkey, pub amount: u64 }
+#[event]
+pub struct InvitesUpdated { pub task: Pubkey, pub task_id: u64, pub invited_agents: Vec<Pubkey> }
+#[event]
+pub struct InviteDeclined { pub task: Pubkey, pub task_id: u64, pub agent: Pubkey, pub remaining: u8 }
 #[event]
-pub struct WinnerSelected { pub task_id: u64, pub agent: Pubkey, pub payout: u64, pub rating: u8 }
+pub struct TaskClaimed { pub task: Pubkey, pub task_id: u64, pub submission: Pubkey, pub agent: Pubkey }
 #[event]
-pub struct TaskCancelled { pub task_id: u64 }
+pub struct WorkSubmitted {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub submission_url: String,
+    pub submission_notes: String,
+    pub submitted_at: i64,
+}
+#[event]
+pub struct WorkRejected {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub reason: String,
+    pub rejection_count: u8,
+}
+#[event]
+pub struct ArbiterUpdated { pub arbiter: Option<Pubkey> }
+#[event]
+pub struct FeeUpdated { pub old_fee_bps: u16, pub new_fee_bps: u16 }
+#[event]
+pub struct AuthorityProposed { pub current: Pubkey, pub proposed: Pubkey }
+#[event]
+pub struct AuthorityTransferred { pub previous: Pubkey, pub new_authority: Pubkey }
+#[event]
+pub struct SubmissionNotSelected { pub task: Pubkey, pub task_id: u64, pub submission: Pubkey, pub agent: Pubkey }
+#[event]
+pub struct SubmissionClosed { pub task: Pubkey, pub task_id: u64, pub submission: Pubkey, pub agent: Pubkey }
 #[event]
-pub struct ExtensionRequested { pub task_id: u64, pub agent: Pubkey, pub extra_hours: u64 }
+pub struct MessageClosed { pub task: Pubkey, pub task_id: u64, pub message_id: u64 }
 #[event]
-pub struct ExtensionApproved { pub task_id: u64, pub new_deadline: i64 }
+pub struct TaskClosed { pub task: Pubkey, pub task_id: u64 }
 #[event]
-pub struct ExtensionDenied { pub task_id: u64 }
+pub struct RetentionPeriodUpdated { pub retention_period: i64 }
 #[event]
-pub struct DisputeRaised { pub task_id: u64, pub raised_by: Pubkey }
+pub struct DisputeBondUpdated { pub dispute_bond: u64 }
 #[event]
-pub struct AutoRefunded { pub task_id: u64 }
+pub struct SlashRateUpdated { pub slash_bps: u16 }
+#[event]
+pub struct Staked { pub agent: Pubkey, pub amount: u64, pub total_staked: u64 }
+#[event]
+pub struct UnstakeRequested { pub agent: Pubkey, pub amount: u64, pub available_at: i64 }
+#[event]
+pub struct StakeWithdrawn { pub agent: Pubkey, pub amount: u64 }
+#[event]
+pub struct StakeSlashed { pub agent: Pubkey, pub amount: u64, pub destination: Pubkey }
+#[event]
+pub struct FeesWithdrawn { pub destination: Pubkey, pub amount: u64, pub mint: Option<Pubkey> }
+#[event]
+pub struct PauseToggled { pub paused: bool }
+#[event]
+pub struct JudgeUpdated { pub judge: Option<Pubkey> }
+#[event]
+pub struct VerdictIssued {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub judge: Pubkey,
+    pub submission: Pubkey,
+    pub agent: Pubkey,
+    pub rating: u8,
+    pub challenge_ends: i64,
+}
+#[event]
+pub struct DisputeResolved {
+    pub task: Pubkey,
+    pub task_id: u64,
+    pub dispute: Pubkey,
+    pub arbiter: Pubkey,
+    pub resolution: DisputeResolution,
+    pub agent: Option<Pubkey>,
+    pub payout: u64,
+    pub fee: u64,
+    pub refund: u64,
+    pub slashed: u64,
+    pub bond_forfeited: u64,
+}
+#[event]
+pub struct AccountMigrated { pub account: Pubkey, pub old_space: u64, pub new_space: u64 }
 
 // ============ ERRORS ============
 
@@ -587,4 +4765,232 @@ pub enum OpenfourrError {
     #[msg("No extension requested")] NoExtensionRequested,
     #[msg("No submissions")] NoSubmissions,
     #[msg("Grace period not over")] GracePeriodNotOver,
+    #[msg("Not arbiter")] NotArbiter,
+    #[msg("Task not disputed")] TaskNotDisputed,
+    #[msg("Invalid split")] InvalidSplit,
+    #[msg("Submission required")] SubmissionRequired,
+    #[msg("Submission belongs to another task")] SubmissionTaskMismatch,
+    #[msg("Agent mismatch")] AgentMismatch,
+    #[msg("Token account required")] TokenAccountRequired,
+    #[msg("Invalid escrow token account")] InvalidEscrowTokenAccount,
+    #[msg("Mint mismatch")] MintMismatch,
+    #[msg("Token account owner mismatch")] TokenAccountOwnerMismatch,
+    #[msg("Escrow underfunded")] EscrowUnderfunded,
+    #[msg("Wrong task mode")] WrongTaskMode,
+    #[msg("Task not in progress")] TaskNotInProgress,
+    #[msg("Task not pending review")] TaskNotPendingReview,
+    #[msg("Not assigned agent")] NotAssignedAgent,
+    #[msg("Reason too long")] ReasonTooLong,
+    #[msg("No judge configured")] NoJudgeConfigured,
+    #[msg("Not task judge")] NotJudge,
+    #[msg("Verdict pending")] VerdictPending,
+    #[msg("No verdict")] NoVerdict,
+    #[msg("Verdict is for another submission")] VerdictSubmissionMismatch,
+    #[msg("Challenge window still open")] ChallengeWindowOpen,
+    #[msg("Invalid task options")] InvalidTaskOptions,
+    #[msg("Task requires a sealed commitment")] CommitRequired,
+    #[msg("Task is not commit-reveal")] NotCommitReveal,
+    #[msg("Already revealed")] AlreadyRevealed,
+    #[msg("Reveal not started")] RevealNotStarted,
+    #[msg("Reveal window closed")] RevealWindowClosed,
+    #[msg("Commitment mismatch")] CommitmentMismatch,
+    #[msg("Submission not revealed")] SubmissionNotRevealed,
+    #[msg("Invalid milestone count")] InvalidMilestoneCount,
+    #[msg("Milestones must sum to the bounty")] MilestoneSumMismatch,
+    #[msg("Invalid milestone")] InvalidMilestone,
+    #[msg("Milestones must be released in order")] MilestoneOutOfOrder,
+    #[msg("Not authorized to release milestones")] NotMilestoneReleaser,
+    #[msg("Task pays out by milestone")] MilestoneTask,
+    #[msg("Agent has open submissions")] HasOpenSubmissions,
+    #[msg("Invalid fee")] InvalidFee,
+    #[msg("Not pending authority")] NotPendingAuthority,
+    #[msg("Platform paused")] PlatformPaused,
+    #[msg("Invalid amount")] InvalidAmount,
+    #[msg("Insufficient treasury balance")] InsufficientTreasury,
+    #[msg("Invalid retention period")] InvalidRetentionPeriod,
+    #[msg("Task not finished")] TaskNotFinished,
+    #[msg("Retention period still active")] RetentionPeriodActive,
+    #[msg("Agent profile required")] AgentProfileRequired,
+    #[msg("Invalid remaining accounts")] InvalidRemainingAccounts,
+    #[msg("Has messages")] HasMessages,
+    #[msg("Milestone plan required")] MilestonePlanRequired,
+    #[msg("No bid accepted")] NoAcceptedBid,
+    #[msg("Bid already accepted")] BidAlreadyAccepted,
+    #[msg("Invalid bid price")] InvalidBidPrice,
+    #[msg("Agent not invited to this task")] NotInvited,
+    #[msg("Too many invited agents")] TooManyInvites,
+    #[msg("Agent invited twice")] DuplicateInvite,
+    #[msg("Task has milestones")] HasMilestones,
+    #[msg("Task is not crowdfunded")] NotCrowdfunded,
+    #[msg("Client cannot contribute")] ClientCannotContribute,
+    #[msg("Already voted")] AlreadyVoted,
+    #[msg("Funders voted for another submission")] OverruledByFunders,
+    #[msg("Task has unclaimed contributions")] HasContributions,
+    #[msg("Invalid slash rate")] InvalidSlash,
+    #[msg("Agent still has stake")] HasStake,
+    #[msg("Insufficient stake")] InsufficientStake,
+    #[msg("Nothing to unstake")] NothingToUnstake,
+    #[msg("Unstake cooldown active")] UnstakeCooldownActive,
+    #[msg("Stake vault required")] StakeVaultRequired,
+    #[msg("Invalid stake vault")] InvalidStakeVault,
+    #[msg("Not the winning agent")] NotWinner,
+    #[msg("Already rated")] AlreadyRated,
+    #[msg("Invalid payout schedule")] InvalidPayoutSchedule,
+    #[msg("Task pays multiple winners; use select_winners")] MultipleWinnersRequired,
+    #[msg("Task pays a single winner")] SingleWinnerTask,
+    #[msg("Submission listed twice")] DuplicateWinner,
+    #[msg("Arithmetic overflow")] MathOverflow,
+    #[msg("Only the client or a submitting agent can do this")] NotParticipant,
+    #[msg("Name empty")] NameEmpty,
+    #[msg("Skill too long")] SkillTooLong,
+    #[msg("Skill empty")] SkillEmpty,
+    #[msg("Title empty")] TitleEmpty,
+    #[msg("Description empty")] DescriptionEmpty,
+    #[msg("Requirements too long")] RequirementsTooLong,
+    #[msg("Category too long")] CategoryTooLong,
+    #[msg("Category empty")] CategoryEmpty,
+    #[msg("URL empty")] UrlEmpty,
+    #[msg("Notes too long")] NotesTooLong,
+    #[msg("Milestone title too long")] MilestoneTitleTooLong,
+    #[msg("Milestone title empty")] MilestoneTitleEmpty,
+    #[msg("Reason empty")] ReasonEmpty,
+    #[msg("Evidence URI too long")] EvidenceUriTooLong,
+    #[msg("Name contains control characters")] NameInvalidCharacters,
+    #[msg("Has bids")] HasBids,
+    #[msg("Milestone deadline has not passed")] MilestoneNotOverdue,
+    #[msg("An unstake is already pending")] UnstakePending,
+    #[msg("Winner can still rate the client")] ClientRatingWindowActive,
+    #[msg("Not an account in the v0 layout")] NotLegacyAccount,
+    #[msg("Reveal window is still open")] RevealWindowOpen,
+    #[msg("Challenge window has closed")] ChallengeWindowClosed,
+    #[msg("Bio contains control characters")] BioInvalidCharacters,
+    #[msg("Skill contains control characters")] SkillInvalidCharacters,
+    #[msg("Title contains control characters")] TitleInvalidCharacters,
+    #[msg("Description contains control characters")] DescriptionInvalidCharacters,
+    #[msg("Requirements contains control characters")] RequirementsInvalidCharacters,
+    #[msg("Category contains control characters")] CategoryInvalidCharacters,
+    #[msg("URL contains control characters")] UrlInvalidCharacters,
+    #[msg("Notes contains control characters")] NotesInvalidCharacters,
+    #[msg("Message contains control characters")] MessageInvalidCharacters,
+    #[msg("Milestone title contains control characters")] MilestoneTitleInvalidCharacters,
+    #[msg("Reason contains control characters")] ReasonInvalidCharacters,
+    #[msg("Evidence URI contains control characters")] EvidenceUriInvalidCharacters,
+    #[msg("Bids stay open until one is accepted or the task ends")] BiddingOpen,
+    #[msg("Funders cannot add to a contribution after voting")] ContributionLocked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overflow() -> anchor_lang::error::Error {
+        OpenfourrError::MathOverflow.into()
+    }
+
+    #[test]
+    fn split_fee_handles_max_bounty() {
+        let (payout, fee) = split_fee(u64::MAX, MAX_FEE_BPS).unwrap();
+        assert_eq!(fee, u64::MAX / 10);
+        assert_eq!(payout + fee, u64::MAX);
+    }
+
+    #[test]
+    fn split_fee_rounds_in_favour_of_agent() {
+        assert_eq!(split_fee(0, MAX_FEE_BPS).unwrap(), (0, 0));
+        assert_eq!(split_fee(9, MAX_FEE_BPS).unwrap(), (9, 0));
+        assert_eq!(split_fee(10, MAX_FEE_BPS).unwrap(), (9, 1));
+        assert_eq!(split_fee(1_000, 0).unwrap(), (1_000, 0));
+    }
+
+    #[test]
+    fn bps_of_bounds() {
+        assert_eq!(bps_of(u64::MAX, 10000).unwrap(), u64::MAX);
+        assert_eq!(bps_of(u64::MAX, 0).unwrap(), 0);
+        assert_eq!(bps_of(u64::MAX, 5000).unwrap(), u64::MAX / 2);
+        assert_eq!(bps_of(u64::MAX, u16::MAX).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn mul_div_rejects_overflow_and_zero_denominator() {
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(mul_div(u64::MAX, 2, 1).unwrap_err(), overflow());
+        assert_eq!(mul_div(1, 1, 0).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn hours_after_bounds() {
+        assert_eq!(hours_after(100, 720).unwrap(), 100 + 720 * 3600);
+        assert_eq!(hours_after(0, 0).unwrap(), 0);
+        assert_eq!(hours_after(0, u64::MAX).unwrap_err(), overflow());
+        assert_eq!(hours_after(i64::MAX - 3599, 1).unwrap_err(), overflow());
+    }
+
+    fn err(error: OpenfourrError) -> anchor_lang::error::Error {
+        error.into()
+    }
+
+    #[test]
+    fn check_text_limits_and_blanks() {
+        let long = "a".repeat(MAX_NAME_LEN + 1);
+        assert!(validate_agent_profile(&"a".repeat(MAX_NAME_LEN), "", &[]).is_ok());
+        assert_eq!(validate_agent_profile(&long, "", &[]).unwrap_err(), err(OpenfourrError::NameTooLong));
+        assert_eq!(validate_agent_profile("  ", "", &[]).unwrap_err(), err(OpenfourrError::NameEmpty));
+        assert_eq!(
+            validate_agent_profile("agent", "", &["rust".into(), String::new()]).unwrap_err(),
+            err(OpenfourrError::SkillEmpty)
+        );
+        assert_eq!(
+            validate_agent_profile("agent", "", &["x".repeat(MAX_SKILL_LEN + 1)]).unwrap_err(),
+            err(OpenfourrError::SkillTooLong)
+        );
+        // Byte length, not characters: 17 two-byte characters overflow a 32-byte field
+        assert_eq!(validate_agent_profile(&"é".repeat(17), "", &[]).unwrap_err(), err(OpenfourrError::NameTooLong));
+    }
+
+    #[test]
+    fn check_text_control_characters() {
+        assert!(validate_submission("https://example.com", "line one\n\tline two").is_ok());
+        assert_eq!(
+            validate_submission("https://example.com\n", "").unwrap_err(),
+            err(OpenfourrError::UrlInvalidCharacters)
+        );
+        assert_eq!(
+            validate_submission("https://example.com", "bell\u{7}").unwrap_err(),
+            err(OpenfourrError::NotesInvalidCharacters)
+        );
+        assert_eq!(validate_submission("", "").unwrap_err(), err(OpenfourrError::UrlEmpty));
+        // Each field reports its own error
+        assert_eq!(
+            validate_task_details("title\n", "description", "", "dev", 24).unwrap_err(),
+            err(OpenfourrError::TitleInvalidCharacters)
+        );
+        assert_eq!(validate_reason("spam\u{1b}").unwrap_err(), err(OpenfourrError::ReasonInvalidCharacters));
+    }
+
+    #[test]
+    fn task_details_cover_every_field() {
+        assert!(validate_task_details("title", "description", "", "dev", 24).is_ok());
+        assert_eq!(
+            validate_task_details("title", "description", &"r".repeat(MAX_REQUIREMENTS_LEN + 1), "dev", 24).unwrap_err(),
+            err(OpenfourrError::RequirementsTooLong)
+        );
+        assert_eq!(
+            validate_task_details("title", "description", "", "", 24).unwrap_err(),
+            err(OpenfourrError::CategoryEmpty)
+        );
+        assert_eq!(
+            validate_task_details("title", "\n", "", "dev", 24).unwrap_err(),
+            err(OpenfourrError::DescriptionEmpty)
+        );
+    }
+
+    #[test]
+    fn legacy_layouts_match_v0_account_sizes() {
+        // `read_legacy` tells v0 accounts apart by size, so these must stay what v0 allocated
+        assert_eq!(8 + PlatformV0::INIT_SPACE, 67);
+        assert_eq!(8 + TaskV0::INIT_SPACE, 3323);
+        assert_eq!(8 + AgentProfileV0::INIT_SPACE, 1002);
+        assert_eq!(8 + SubmissionV0::INIT_SPACE, 1566);
+        assert_eq!(8 + MessageV0::INIT_SPACE, 569);
+    }
 }
@@ -0,0 +1,1294 @@
+//! End-to-end escrow flows against the program under `solana-program-test`
+//!
+//! Anchor's CPIs only work on-chain, so these load the SBF build and run under
+//! `cargo test-sbf --manifest-path programs/openfourr/Cargo.toml`, which builds it first.
+//!
+//! Instructions come from `openfourr-client`, so these also check that its builders pass the
+//! accounts each context expects.
+#![cfg(feature = "test-sbf")]
+
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::prelude::Clock;
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, AnchorSerialize, Discriminator, Space};
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::{spl_token, TokenAccount};
+use openfourr::{
+    submission_commitment, OpenfourrError, UnfilledPlacesRefunded, WorkRejected, JUDGE_CHALLENGE_WINDOW, REVEAL_WINDOW,
+};
+use openfourr_client::{
+    instructions, pda, state, AgentProfile, ClientProfile, DisputeResolution, MilestoneInput, Pubkey, SlashTarget, SplMint, Submission,
+    SubmissionStatus, Task, TaskMode, TaskOptions, TaskStatus, ID,
+};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+const FEE_BPS: u16 = 500;
+const SOL: u64 = 1_000_000_000;
+
+struct Harness {
+    context: ProgramTestContext,
+}
+
+impl Harness {
+    /// Boot a bank with the program loaded and the platform initialized at `FEE_BPS`
+    async fn start() -> Self {
+        let mut test = ProgramTest::new("openfourr", ID, None);
+        test.prefer_bpf(true);
+        let mut harness = Self { context: test.start_with_context().await };
+
+        let authority = harness.authority();
+        harness.send(instructions::initialize(&authority, FEE_BPS), &[]).await.unwrap();
+        harness
+    }
+
+    /// Platform authority, also the fee payer of every transaction
+    fn authority(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    async fn transaction(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Transaction {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        Transaction::new_signed_with_payer(&[instruction], Some(&self.context.payer.pubkey()), &all_signers, blockhash)
+    }
+
+    async fn send(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let transaction = self.transaction(instruction, signers).await;
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    /// Send `instruction` and decode the `T` events it emitted through `emit_cpi!`
+    async fn send_for_events<T: AnchorDeserialize + Discriminator>(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Vec<T> {
+        let transaction = self.transaction(instruction, signers).await;
+        let simulation = self.context.banks_client.simulate_transaction(transaction.clone()).await.unwrap();
+        self.context.banks_client.process_transaction(transaction).await.unwrap();
+
+        let inner_instructions = simulation.simulation_details.and_then(|details| details.inner_instructions);
+        inner_instructions
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|inner| {
+                let data = inner.instruction.data.strip_prefix(EVENT_IX_TAG_LE)?.strip_prefix(T::DISCRIMINATOR)?;
+                T::try_from_slice(data).ok()
+            })
+            .collect()
+    }
+
+    /// A fresh wallet holding `lamports`
+    async fn wallet(&mut self, lamports: u64) -> Keypair {
+        let wallet = Keypair::new();
+        let payer = self.authority();
+        self.send(system_instruction::transfer(&payer, &wallet.pubkey(), lamports), &[]).await.unwrap();
+        wallet
+    }
+
+    /// A fresh wallet with a registered agent profile
+    async fn agent(&mut self, name: &str) -> Keypair {
+        let agent = self.wallet(SOL).await;
+        let register = instructions::register_agent(&agent.pubkey(), name.to_string(), String::new(), vec![], 0);
+        self.send(register, &[&agent]).await.unwrap();
+        agent
+    }
+
+    async fn create_task(&mut self, client: &Keypair, bounty_amount: u64, options: TaskOptions, spl: Option<SplMint>) -> Pubkey {
+        let create = instructions::create_task(
+            &client.pubkey(),
+            0,
+            "Label the dataset".to_string(),
+            "Label 1000 images".to_string(),
+            "CSV output".to_string(),
+            "data".to_string(),
+            bounty_amount,
+            48,
+            options,
+            spl,
+        );
+        self.send(create, &[client]).await.unwrap();
+        pda::task(0)
+    }
+
+    /// Create a mint owned by the fee payer and fund an associated token account for each of `holders`
+    async fn mint(&mut self, holders: &[(Pubkey, u64)]) -> SplMint {
+        let mint = Keypair::new();
+        let payer = self.authority();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let space = spl_token::state::Mint::LEN;
+        let create = system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(space),
+            space as u64,
+            &spl_token::ID,
+        );
+        self.send(create, &[&mint]).await.unwrap();
+        let initialize = spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer, None, 6).unwrap();
+        self.send(initialize, &[]).await.unwrap();
+
+        let spl = SplMint::new(mint.pubkey(), spl_token::ID);
+        for (owner, amount) in holders {
+            self.token_account(spl, owner).await;
+            let mint_to =
+                spl_token::instruction::mint_to(&spl_token::ID, &spl.mint, &spl.ata(owner), &payer, &[], *amount).unwrap();
+            self.send(mint_to, &[]).await.unwrap();
+        }
+        spl
+    }
+
+    async fn token_account(&mut self, spl: SplMint, owner: &Pubkey) {
+        let payer = self.authority();
+        let create = create_associated_token_account_idempotent(&payer, owner, &spl.mint, &spl.token_program);
+        self.send(create, &[]).await.unwrap();
+    }
+
+    async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.context.banks_client.get_balance(*address).await.unwrap()
+    }
+
+    async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.context.banks_client.get_account(*address).await.unwrap().is_some()
+    }
+
+    async fn read<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.context.banks_client.get_account(*address).await.unwrap().expect("account exists");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn token_balance(&mut self, spl: SplMint, owner: &Pubkey) -> u64 {
+        self.read::<TokenAccount>(&spl.ata(owner)).await.amount
+    }
+
+    /// Store an account as-is, bypassing the program
+    async fn set_account(&mut self, address: &Pubkey, owner: &Pubkey, lamports: u64, data: Vec<u8>) {
+        let account = Account { lamports, data, owner: *owner, executable: false, rent_epoch: 0 };
+        self.context.set_account(address, &account.into());
+    }
+
+    /// Move the bank clock to `unix_timestamp`
+    async fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock = self.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Lamports a SOL escrow keeps on top of the bounty so it stays rent exempt
+    async fn rent_reserve(&mut self) -> u64 {
+        self.context.banks_client.get_rent().await.unwrap().minimum_balance(0)
+    }
+}
+
+fn options(mode: TaskMode) -> TaskOptions {
+    TaskOptions {
+        mode,
+        use_judge: false,
+        commit_reveal: false,
+        invited_agents: vec![],
+        direct_hire: None,
+        crowdfunded: false,
+        min_stake: 0,
+        payout_schedule: vec![],
+    }
+}
+
+fn after_fee(amount: u64) -> u64 {
+    amount - amount * FEE_BPS as u64 / 10_000
+}
+
+/// Assert the transaction was rejected by the program with `error`
+fn assert_rejected(result: Result<(), BanksClientError>, error: OpenfourrError) {
+    match result.expect_err("transaction should fail").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, u32::from(error)),
+        other => panic!("expected {error:?}, got {other:?}"),
+    }
+}
+
+fn submit(agent: &Keypair, task: &Pubkey) -> Instruction {
+    instructions::submit_application(&agent.pubkey(), task, "https://example.com/labels.csv".to_string(), String::new())
+}
+
+#[tokio::test]
+async fn spl_escrow_deposits_pays_and_closes() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(SOL).await;
+    let agent = harness.agent("labeler").await;
+    let spl = harness.mint(&[(client.pubkey(), 1_000_000)]).await;
+    harness.token_account(spl, &agent.pubkey()).await;
+    harness.token_account(spl, &pda::treasury()).await;
+
+    let task = harness.create_task(&client, 400_000, options(TaskMode::OpenCompetition), Some(spl)).await;
+    let escrow = pda::escrow(0);
+    assert_eq!(harness.token_balance(spl, &escrow).await, 400_000);
+    assert_eq!(harness.token_balance(spl, &client.pubkey()).await, 600_000);
+
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, Some(spl));
+    harness.send(select, &[&client]).await.unwrap();
+
+    assert_eq!(harness.token_balance(spl, &agent.pubkey()).await, after_fee(400_000));
+    assert_eq!(harness.token_balance(spl, &pda::treasury()).await, 400_000 - after_fee(400_000));
+    assert!(!harness.exists(&spl.ata(&escrow)).await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+}
+
+#[tokio::test]
+async fn spl_escrow_sweeps_donated_tokens_to_the_client_on_close() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(SOL).await;
+    let agent = harness.agent("labeler").await;
+    let spl = harness.mint(&[(client.pubkey(), 1_000_000)]).await;
+    harness.token_account(spl, &agent.pubkey()).await;
+    harness.token_account(spl, &pda::treasury()).await;
+
+    let task = harness.create_task(&client, 400_000, options(TaskMode::OpenCompetition), Some(spl)).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+
+    // Anyone can send tokens to the escrow token account; they must not keep it from closing
+    let authority = harness.authority();
+    let donate = spl_token::instruction::mint_to(&spl_token::ID, &spl.mint, &spl.ata(&escrow), &authority, &[], 7).unwrap();
+    harness.send(donate, &[]).await.unwrap();
+
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, Some(spl));
+    harness.send(select, &[&client]).await.unwrap();
+
+    assert_eq!(harness.token_balance(spl, &agent.pubkey()).await, after_fee(400_000));
+    assert_eq!(harness.token_balance(spl, &client.pubkey()).await, 600_007);
+    assert!(!harness.exists(&spl.ata(&escrow)).await);
+}
+
+#[tokio::test]
+async fn spl_payout_opens_missing_agent_and_treasury_token_accounts() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(SOL).await;
+    let agent = harness.agent("labeler").await;
+    let spl = harness.mint(&[(client.pubkey(), 1_000_000)]).await;
+    assert!(!harness.exists(&spl.ata(&agent.pubkey())).await);
+    assert!(!harness.exists(&spl.ata(&pda::treasury())).await);
+
+    let task = harness.create_task(&client, 400_000, options(TaskMode::OpenCompetition), Some(spl)).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+
+    // The client signs the payout, so it fronts the rent of both new ATAs and gets the escrow ATA's back
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let escrow_ata_rent = harness.lamports(&spl.ata(&escrow)).await;
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, Some(spl));
+    harness.send(select, &[&client]).await.unwrap();
+
+    assert_eq!(harness.token_balance(spl, &agent.pubkey()).await, after_fee(400_000));
+    assert_eq!(harness.token_balance(spl, &pda::treasury()).await, 400_000 - after_fee(400_000));
+    let ata_rent = harness.lamports(&spl.ata(&agent.pubkey())).await;
+    assert_eq!(harness.lamports(&client.pubkey()).await, client_before + escrow_ata_rent - 2 * ata_rent);
+
+    // Fee withdrawals open the destination's ATA too
+    let destination = Keypair::new().pubkey();
+    let authority = harness.authority();
+    harness.send(instructions::withdraw_fees(&authority, &destination, 20_000, Some(spl)), &[]).await.unwrap();
+    assert_eq!(harness.token_balance(spl, &destination).await, 20_000);
+}
+
+#[tokio::test]
+async fn migrate_task_upgrades_a_v0_task_and_its_escrow() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(SOL).await;
+    let (task, task_bump) = Pubkey::find_program_address(&[b"task", 0u64.to_le_bytes().as_ref()], &ID);
+    let (escrow, escrow_bump) = Pubkey::find_program_address(&[b"escrow", 0u64.to_le_bytes().as_ref()], &ID);
+
+    // An open v0 task: its fields in v0 order, zero-padded to the v0 size, with a bare SOL escrow
+    let mut data = Task::DISCRIMINATOR.to_vec();
+    (0u64, client.pubkey(), "Label the dataset".to_string(), "Label 1000 images".to_string()).serialize(&mut data).unwrap();
+    (String::new(), "data".to_string(), SOL, 0i64, i64::MAX, TaskStatus::Open, 0u64, 0u64).serialize(&mut data).unwrap();
+    (false, 0u64, None::<Pubkey>, None::<Pubkey>, None::<i64>, task_bump, escrow_bump).serialize(&mut data).unwrap();
+    data.resize(8 + openfourr::TaskV0::INIT_SPACE, 0);
+    let rent = harness.context.banks_client.get_rent().await.unwrap();
+    harness.set_account(&task, &ID, rent.minimum_balance(data.len()), data).await;
+    harness.set_account(&escrow, &anchor_lang::system_program::ID, SOL, vec![]).await;
+
+    let authority = harness.authority();
+    harness.send(instructions::migrate_task(&authority, 0), &[]).await.unwrap();
+    assert!(harness.send(instructions::migrate_task(&authority, 0), &[]).await.is_err());
+
+    let reserve = harness.rent_reserve().await;
+    assert_eq!(harness.lamports(&escrow).await, SOL + reserve);
+    let state = harness.read::<Task>(&task).await;
+    assert_eq!((state.id, state.client, state.bounty_amount), (0, client.pubkey(), SOL));
+    assert!(state.mode == TaskMode::OpenCompetition && state.mint.is_none() && !state.per_client);
+
+    // The migrated task runs through the current instructions
+    let client_before = harness.lamports(&client.pubkey()).await;
+    harness.send(instructions::cancel_task(&client.pubkey(), &task, &escrow, false, None), &[&client]).await.unwrap();
+    let client_profile_rent = harness.lamports(&pda::client_profile(&client.pubkey())).await;
+    assert_eq!(harness.lamports(&client.pubkey()).await + client_profile_rent - client_before, SOL + reserve);
+    assert!(!harness.exists(&escrow).await);
+}
+
+#[tokio::test]
+async fn dispute_split_pays_agent_share_and_refunds_the_rest() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("writer").await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let dispute = instructions::raise_dispute(&client.pubkey(), &task, "Off brief".to_string(), String::new(), None);
+    harness.send(dispute, &[&client]).await.unwrap();
+
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let treasury_before = harness.lamports(&pda::treasury()).await;
+    let dispute_lamports = harness.lamports(&pda::dispute(&task)).await;
+    let resolve = instructions::resolve_dispute(
+        &harness.authority(),
+        &task,
+        &escrow,
+        &client.pubkey(),
+        &client.pubkey(),
+        Some(agent.pubkey()),
+        DisputeResolution::Split { agent_bps: 6000 },
+        Some(4),
+        None,
+        true,
+        None,
+    );
+    harness.send(resolve, &[]).await.unwrap();
+
+    let reserve = harness.rent_reserve().await;
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(600_000_000));
+    assert_eq!(harness.lamports(&pda::treasury()).await - treasury_before, 600_000_000 - after_fee(600_000_000));
+    // The client raised the dispute, so the returned bond and the dispute's rent come back with the refund
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 400_000_000 + reserve + dispute_lamports);
+    assert!(!harness.exists(&escrow).await);
+    assert!(!harness.exists(&pda::dispute(&task)).await);
+}
+
+#[tokio::test]
+async fn exclusive_claim_runs_claim_submit_reject_and_approve() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("claimer").await, harness.agent("late").await];
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::ExclusiveClaim), None).await;
+    let escrow = pda::escrow(0);
+    assert!(harness.send(submit(&agents[0], &task), &[&agents[0]]).await.is_err());
+    harness.send(instructions::claim_task(&agents[0].pubkey(), &task), &[&agents[0]]).await.unwrap();
+    assert!(harness.send(instructions::claim_task(&agents[1].pubkey(), &task), &[&agents[1]]).await.is_err());
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::InProgress);
+
+    let work = |agent: &Keypair| {
+        instructions::submit_work(&agent.pubkey(), &task, "https://example.com/v1.csv".to_string(), String::new())
+    };
+    harness.send(work(&agents[0]), &[&agents[0]]).await.unwrap();
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::PendingReview);
+
+    // A rejection sends the task back to the claimer for another attempt
+    let reject = instructions::reject_work(&client.pubkey(), &task, &agents[0].pubkey(), "Missing rows".to_string());
+    harness.send(reject, &[&client]).await.unwrap();
+    let state = harness.read::<Task>(&task).await;
+    assert!(state.status == TaskStatus::InProgress);
+    assert_eq!(state.rejection_count, 1);
+    harness.send(work(&agents[0]), &[&agents[0]]).await.unwrap();
+
+    let agent_before = harness.lamports(&agents[0].pubkey()).await;
+    let approve = instructions::approve_work(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 4, None);
+    harness.send(approve, &[&client]).await.unwrap();
+
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - agent_before, after_fee(SOL));
+    assert!(!harness.exists(&escrow).await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+}
+
+#[tokio::test]
+async fn judge_approval_holds_payout_until_the_challenge_window_closes() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("judged").await;
+    let judge = harness.wallet(SOL).await;
+    let authority = harness.authority();
+    harness.send(instructions::set_judge(&authority, Some(judge.pubkey())), &[]).await.unwrap();
+
+    let task = harness.create_task(&client, SOL, TaskOptions { use_judge: true, ..options(TaskMode::OpenCompetition) }, None).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let verdict = instructions::judge_verdict(&judge.pubkey(), &task, &client.pubkey(), &agent.pubkey(), true, 5, String::new());
+    harness.send(verdict, &[&judge]).await.unwrap();
+
+    // The client can't pay around the verdict, and nobody can finalize it early
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 1, None);
+    assert!(harness.send(select, &[&client]).await.is_err());
+    let finalize = instructions::finalize_verdict(&authority, &task, &escrow, &client.pubkey(), &agent.pubkey(), None);
+    assert!(harness.send(finalize.clone(), &[]).await.is_err());
+
+    let mut clock = harness.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += JUDGE_CHALLENGE_WINDOW;
+    harness.context.set_sysvar(&clock);
+
+    // Once the window closes the verdict stands: no more disputes, and anyone can release the escrow
+    let dispute = instructions::raise_dispute(&client.pubkey(), &task, "Too late".to_string(), String::new(), None);
+    assert!(harness.send(dispute, &[&client]).await.is_err());
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    harness.send(finalize, &[]).await.unwrap();
+
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(SOL));
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+}
+
+#[tokio::test]
+async fn finalize_submissions_keeps_an_unrated_winner_open() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("winner").await, harness.agent("runner-up").await];
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    let escrow = pda::escrow(0);
+    for agent in &agents {
+        harness.send(submit(agent, &task), &[agent]).await.unwrap();
+    }
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+
+    let all = [agents[0].pubkey(), agents[1].pubkey()];
+    assert_rejected(
+        harness.send(instructions::finalize_submissions(&task, &all, true), &[]).await,
+        OpenfourrError::ClientRatingWindowActive,
+    );
+
+    // The loser goes on its own; the winner once it has rated the client
+    harness.send(instructions::finalize_submissions(&task, &all[1..], true), &[]).await.unwrap();
+    assert!(!harness.exists(&pda::submission(&task, &all[1])).await);
+    harness.send(instructions::rate_client(&all[0], &task, &client.pubkey(), 4), &[&agents[0]]).await.unwrap();
+
+    let winner_before = harness.lamports(&all[0]).await;
+    let submission_rent = harness.lamports(&pda::submission(&task, &all[0])).await;
+    harness.send(instructions::finalize_submissions(&task, &all[..1], true), &[]).await.unwrap();
+    assert_eq!(harness.lamports(&all[0]).await - winner_before, submission_rent);
+    assert_eq!(harness.read::<Task>(&task).await.closed_submissions, 2);
+}
+
+#[tokio::test]
+async fn commit_reveal_pays_once_the_reveal_window_settles() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("revealed").await, harness.agent("sealed").await];
+    let url = "https://example.com/labels.csv";
+    let salt = [7; 32];
+
+    let sealed = TaskOptions { commit_reveal: true, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, SOL, sealed, None).await;
+    let escrow = pda::escrow(0);
+    for agent in &agents {
+        let commitment = submission_commitment(&agent.pubkey(), url, "", &salt);
+        harness.send(instructions::commit_submission(&agent.pubkey(), &task, commitment), &[agent]).await.unwrap();
+    }
+    let reveal = |agent: &Keypair, salt: [u8; 32]| {
+        instructions::reveal_submission(&agent.pubkey(), &task, url.to_string(), String::new(), salt)
+    };
+
+    let deadline = harness.read::<Task>(&task).await.deadline;
+    harness.warp_to(deadline).await;
+    assert_rejected(harness.send(reveal(&agents[0], [8; 32]), &[&agents[0]]).await, OpenfourrError::CommitmentMismatch);
+    harness.send(reveal(&agents[0], salt), &[&agents[0]]).await.unwrap();
+    assert_eq!(harness.read::<Task>(&task).await.revealed_count, 1);
+
+    // The other agent may still reveal, so the revealed one can't be picked yet
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 5, None);
+    assert_rejected(harness.send(select.clone(), &[&client]).await, OpenfourrError::RevealWindowOpen);
+
+    harness.warp_to(deadline + REVEAL_WINDOW).await;
+    assert_rejected(harness.send(reveal(&agents[1], salt), &[&agents[1]]).await, OpenfourrError::RevealWindowClosed);
+    // The unrevealed commitment is forfeited while the task is still open
+    harness.send(instructions::finalize_submissions(&task, &[agents[1].pubkey()], false), &[]).await.unwrap();
+    let forfeited = harness.read::<Submission>(&pda::submission(&task, &agents[1].pubkey())).await;
+    assert!(forfeited.status == SubmissionStatus::NotSelected);
+
+    let agent_before = harness.lamports(&agents[0].pubkey()).await;
+    harness.send(select, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - agent_before, after_fee(SOL));
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+}
+
+#[tokio::test]
+async fn commit_reveal_task_cancels_once_every_commitment_is_forfeited() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("silent").await;
+
+    let sealed = TaskOptions { commit_reveal: true, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, SOL, sealed, None).await;
+    let escrow = pda::escrow(0);
+    harness.send(instructions::commit_submission(&agent.pubkey(), &task, [1; 32]), &[&agent]).await.unwrap();
+
+    let cancel = instructions::cancel_task(&client.pubkey(), &task, &escrow, false, None);
+    assert_rejected(harness.send(cancel.clone(), &[&client]).await, OpenfourrError::HasSubmissions);
+
+    let deadline = harness.read::<Task>(&task).await.deadline;
+    harness.warp_to(deadline + REVEAL_WINDOW).await;
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let reserve = harness.rent_reserve().await;
+    harness.send(cancel, &[&client]).await.unwrap();
+
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, SOL + reserve);
+    assert!(!harness.exists(&escrow).await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn milestones_release_in_order_and_close_the_escrow_with_the_last() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("builder").await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::ExclusiveClaim), None).await;
+    let escrow = pda::escrow(0);
+    let plan = |amounts: [u64; 2]| {
+        let milestones = amounts
+            .iter()
+            .zip([24, 48])
+            .map(|(&amount, deadline_hours)| MilestoneInput { title: "Batch".to_string(), amount, deadline_hours })
+            .collect();
+        instructions::add_milestones(&client.pubkey(), &task, milestones)
+    };
+    // The plan must split exactly the bounty
+    assert_rejected(harness.send(plan([400_000_000, 700_000_000]), &[&client]).await, OpenfourrError::MilestoneSumMismatch);
+    harness.send(plan([400_000_000, 600_000_000]), &[&client]).await.unwrap();
+    harness.send(instructions::claim_task(&agent.pubkey(), &task), &[&agent]).await.unwrap();
+
+    let release = |index: u8| {
+        instructions::release_milestone(&client.pubkey(), &task, &escrow, &client.pubkey(), &agent.pubkey(), index, Some(5), None)
+    };
+    assert_rejected(harness.send(release(1), &[&client]).await, OpenfourrError::MilestoneOutOfOrder);
+
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    harness.send(release(0), &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(400_000_000));
+    assert_eq!(harness.lamports(&escrow).await, 600_000_000 + harness.rent_reserve().await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::InProgress);
+
+    let client_before = harness.lamports(&client.pubkey()).await;
+    harness.send(release(1), &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(400_000_000) + after_fee(600_000_000));
+    // Closing the escrow hands its rent reserve back to the client
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, harness.rent_reserve().await);
+    assert!(!harness.exists(&escrow).await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+
+    // Nothing is left to release past the plan
+    assert_rejected(harness.send(release(2), &[&client]).await, OpenfourrError::TaskNotInProgress);
+}
+
+#[tokio::test]
+async fn agent_updates_pauses_and_closes_its_profile() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("before").await;
+    let profile = pda::agent(&agent.pubkey());
+
+    let update = instructions::update_agent(&agent.pubkey(), "after".to_string(), "Labels fast".to_string(), vec![], 10);
+    harness.send(update, &[&agent]).await.unwrap();
+    assert_eq!(harness.read::<AgentProfile>(&profile).await.name, "after");
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    harness.send(instructions::set_agent_active(&agent.pubkey(), false), &[&agent]).await.unwrap();
+    assert_rejected(harness.send(submit(&agent, &task), &[&agent]).await, OpenfourrError::AgentNotActive);
+    harness.send(instructions::set_agent_active(&agent.pubkey(), true), &[&agent]).await.unwrap();
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+
+    let close = instructions::close_agent(&agent.pubkey());
+    assert_rejected(harness.send(close.clone(), &[&agent]).await, OpenfourrError::HasOpenSubmissions);
+
+    let select = instructions::select_winner(&client.pubkey(), &task, &pda::escrow(0), &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    let profile_rent = harness.lamports(&profile).await;
+    harness.send(close, &[&agent]).await.unwrap();
+
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, profile_rent);
+    assert!(!harness.exists(&profile).await);
+}
+
+#[tokio::test]
+async fn authority_updates_fees_pauses_and_hands_over() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("early").await, harness.agent("paused").await];
+    let authority = harness.authority();
+    let successor = harness.wallet(SOL).await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    harness.send(submit(&agents[0], &task), &[&agents[0]]).await.unwrap();
+
+    assert!(harness.send(instructions::update_fee(&successor.pubkey(), 0), &[&successor]).await.is_err());
+    assert_rejected(harness.send(instructions::update_fee(&authority, 1001), &[]).await, OpenfourrError::InvalidFee);
+    harness.send(instructions::update_fee(&authority, 1000), &[]).await.unwrap();
+
+    // Pausing stops new submissions and payouts alike
+    harness.send(instructions::set_paused(&authority, true), &[]).await.unwrap();
+    assert_rejected(harness.send(submit(&agents[1], &task), &[&agents[1]]).await, OpenfourrError::PlatformPaused);
+    let select = instructions::select_winner(&client.pubkey(), &task, &pda::escrow(0), &agents[0].pubkey(), 5, None);
+    assert_rejected(harness.send(select.clone(), &[&client]).await, OpenfourrError::PlatformPaused);
+
+    harness.send(instructions::propose_authority(&authority, successor.pubkey()), &[]).await.unwrap();
+    assert_rejected(
+        harness.send(instructions::accept_authority(&client.pubkey()), &[&client]).await,
+        OpenfourrError::NotPendingAuthority,
+    );
+    harness.send(instructions::accept_authority(&successor.pubkey()), &[&successor]).await.unwrap();
+    assert!(harness.send(instructions::set_paused(&authority, false), &[]).await.is_err());
+    harness.send(instructions::set_paused(&successor.pubkey(), false), &[&successor]).await.unwrap();
+
+    let agent_before = harness.lamports(&agents[0].pubkey()).await;
+    let treasury_before = harness.lamports(&pda::treasury()).await;
+    harness.send(select, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - agent_before, SOL - SOL / 10);
+    assert_eq!(harness.lamports(&pda::treasury()).await - treasury_before, SOL / 10);
+}
+
+#[tokio::test]
+async fn withdraw_fees_drains_the_treasury_down_to_its_rent_reserve() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("earner").await;
+    let authority = harness.authority();
+    let destination = Keypair::new().pubkey();
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let select = instructions::select_winner(&client.pubkey(), &task, &pda::escrow(0), &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+
+    let reserve = harness.rent_reserve().await;
+    let withdrawable = harness.lamports(&pda::treasury()).await - reserve;
+    assert_eq!(withdrawable, SOL - after_fee(SOL));
+
+    let withdraw = |amount| instructions::withdraw_fees(&authority, &destination, amount, None);
+    assert!(harness.send(instructions::withdraw_fees(&client.pubkey(), &destination, 1, None), &[&client]).await.is_err());
+    assert_rejected(harness.send(withdraw(0), &[]).await, OpenfourrError::InvalidAmount);
+    assert_rejected(harness.send(withdraw(withdrawable + 1), &[]).await, OpenfourrError::InsufficientTreasury);
+    harness.send(withdraw(withdrawable), &[]).await.unwrap();
+
+    assert_eq!(harness.lamports(&destination).await, withdrawable);
+    assert_eq!(harness.lamports(&pda::treasury()).await, reserve);
+}
+
+#[tokio::test]
+async fn finished_task_accounts_close_to_their_payers_after_retention() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("tidy").await;
+    let authority = harness.authority();
+    harness.send(instructions::set_retention_period(&authority, 3600), &[]).await.unwrap();
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let message = instructions::send_message(&client.pubkey(), &task, 0, "Thanks".to_string(), None);
+    harness.send(message, &[&client]).await.unwrap();
+    let select = instructions::select_winner(&client.pubkey(), &task, &pda::escrow(0), &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+    harness.send(instructions::rate_client(&agent.pubkey(), &task, &client.pubkey(), 5), &[&agent]).await.unwrap();
+
+    let close_task = instructions::close_task(&task, &client.pubkey(), false);
+    assert_rejected(harness.send(close_task.clone(), &[]).await, OpenfourrError::RetentionPeriodActive);
+    let completed_at = harness.read::<Task>(&task).await.completed_at.unwrap();
+    harness.warp_to(completed_at + 3600).await;
+    assert_rejected(harness.send(close_task.clone(), &[]).await, OpenfourrError::HasSubmissions);
+
+    // Each account's rent goes back to whoever paid for it
+    let submission = pda::submission(&task, &agent.pubkey());
+    let (agent_before, submission_rent) = (harness.lamports(&agent.pubkey()).await, harness.lamports(&submission).await);
+    harness.send(instructions::close_submission(&task, &agent.pubkey(), true), &[]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, submission_rent);
+
+    let message = pda::message(&task, 0);
+    let (client_before, message_rent) = (harness.lamports(&client.pubkey()).await, harness.lamports(&message).await);
+    harness.send(instructions::close_message(&task, 0, &client.pubkey()), &[]).await.unwrap();
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, message_rent);
+
+    let (client_before, task_rent) = (harness.lamports(&client.pubkey()).await, harness.lamports(&task).await);
+    harness.send(close_task, &[]).await.unwrap();
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, task_rent);
+    for account in [submission, message, task] {
+        assert!(!harness.exists(&account).await);
+    }
+}
+
+#[tokio::test]
+async fn accepted_bid_refunds_the_unused_budget_and_pays_the_bidder() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("cheap").await, harness.agent("pricey").await];
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::Bidding), None).await;
+    let escrow = pda::escrow(0);
+    let bid = |agent: &Keypair, price| instructions::submit_bid(&agent.pubkey(), &task, price, 24);
+    assert_rejected(harness.send(bid(&agents[0], SOL + 1), &[&agents[0]]).await, OpenfourrError::InvalidBidPrice);
+    assert_rejected(
+        harness.send(instructions::claim_task(&agents[0].pubkey(), &task), &[&agents[0]]).await,
+        OpenfourrError::NoAcceptedBid,
+    );
+    harness.send(bid(&agents[0], 600_000_000), &[&agents[0]]).await.unwrap();
+    harness.send(bid(&agents[1], 800_000_000), &[&agents[1]]).await.unwrap();
+    harness.send(instructions::withdraw_bid(&agents[1].pubkey(), &task), &[&agents[1]]).await.unwrap();
+    assert!(!harness.exists(&pda::bid(&task, &agents[1].pubkey())).await);
+
+    let (client_before, agent_before) = (harness.lamports(&client.pubkey()).await, harness.lamports(&agents[0].pubkey()).await);
+    let bid_rent = harness.lamports(&pda::bid(&task, &agents[0].pubkey())).await;
+    let accept = instructions::accept_bid(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), None);
+    harness.send(accept, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 400_000_000);
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - agent_before, bid_rent);
+    assert_eq!(harness.read::<Task>(&task).await.bounty_amount, 600_000_000);
+
+    assert_rejected(
+        harness.send(instructions::claim_task(&agents[1].pubkey(), &task), &[&agents[1]]).await,
+        OpenfourrError::NotAssignedAgent,
+    );
+    harness.send(instructions::claim_task(&agents[0].pubkey(), &task), &[&agents[0]]).await.unwrap();
+    let work = instructions::submit_work(&agents[0].pubkey(), &task, "https://example.com/v1.csv".to_string(), String::new());
+    harness.send(work, &[&agents[0]]).await.unwrap();
+
+    let agent_before = harness.lamports(&agents[0].pubkey()).await;
+    let approve = instructions::approve_work(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 5, None);
+    harness.send(approve, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - agent_before, after_fee(600_000_000));
+    assert!(!harness.exists(&escrow).await);
+}
+
+#[tokio::test]
+async fn losing_bid_closes_without_its_bidder_so_the_task_can_close() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("winner").await, harness.agent("idle").await];
+    let authority = harness.authority();
+    harness.send(instructions::set_retention_period(&authority, 3600), &[]).await.unwrap();
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::Bidding), None).await;
+    let escrow = pda::escrow(0);
+    for agent in &agents {
+        harness.send(instructions::submit_bid(&agent.pubkey(), &task, 500_000_000, 24), &[agent]).await.unwrap();
+    }
+    let close_bid = instructions::close_bid(&task, &agents[1].pubkey());
+    assert_rejected(harness.send(close_bid.clone(), &[]).await, OpenfourrError::BiddingOpen);
+
+    let accept = instructions::accept_bid(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), None);
+    harness.send(accept, &[&client]).await.unwrap();
+
+    // The losing bidder never withdraws; anyone returns its rent once the task is assigned
+    let bid = pda::bid(&task, &agents[1].pubkey());
+    let (agent_before, bid_rent) = (harness.lamports(&agents[1].pubkey()).await, harness.lamports(&bid).await);
+    harness.send(close_bid, &[]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[1].pubkey()).await - agent_before, bid_rent);
+    assert!(!harness.exists(&bid).await);
+    assert_eq!(harness.read::<Task>(&task).await.bid_count, 0);
+
+    harness.send(instructions::claim_task(&agents[0].pubkey(), &task), &[&agents[0]]).await.unwrap();
+    let work = instructions::submit_work(&agents[0].pubkey(), &task, "https://example.com/v1.csv".to_string(), String::new());
+    harness.send(work, &[&agents[0]]).await.unwrap();
+    let approve = instructions::approve_work(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 5, None);
+    harness.send(approve, &[&client]).await.unwrap();
+    harness.send(instructions::rate_client(&agents[0].pubkey(), &task, &client.pubkey(), 5), &[&agents[0]]).await.unwrap();
+
+    let completed_at = harness.read::<Task>(&task).await.completed_at.unwrap();
+    harness.warp_to(completed_at + 3600).await;
+    harness.send(instructions::close_submission(&task, &agents[0].pubkey(), true), &[]).await.unwrap();
+    harness.send(instructions::close_task(&task, &client.pubkey(), false), &[]).await.unwrap();
+    assert!(!harness.exists(&task).await);
+}
+
+#[tokio::test]
+async fn direct_hire_only_lets_the_invited_agent_claim() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("hired").await, harness.agent("outsider").await];
+
+    let hire = TaskOptions { direct_hire: Some(agents[0].pubkey()), ..options(TaskMode::ExclusiveClaim) };
+    let task = harness.create_task(&client, SOL, hire, None).await;
+    let claim = |agent: &Keypair| instructions::claim_task(&agent.pubkey(), &task);
+    assert_rejected(harness.send(claim(&agents[1]), &[&agents[1]]).await, OpenfourrError::NotInvited);
+
+    // Once the hire declines, only the client can open the task up again
+    harness.send(instructions::decline_invite(&agents[0].pubkey(), &task), &[&agents[0]]).await.unwrap();
+    assert_rejected(harness.send(claim(&agents[0]), &[&agents[0]]).await, OpenfourrError::NotInvited);
+    let invite = instructions::set_invited_agents(&client.pubkey(), &task, vec![agents[1].pubkey()]);
+    harness.send(invite, &[&client]).await.unwrap();
+    harness.send(claim(&agents[1]), &[&agents[1]]).await.unwrap();
+    let work = instructions::submit_work(&agents[1].pubkey(), &task, "https://example.com/v1.csv".to_string(), String::new());
+    harness.send(work, &[&agents[1]]).await.unwrap();
+
+    let agent_before = harness.lamports(&agents[1].pubkey()).await;
+    let approve = instructions::approve_work(&client.pubkey(), &task, &pda::escrow(0), &agents[1].pubkey(), 5, None);
+    harness.send(approve, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[1].pubkey()).await - agent_before, after_fee(SOL));
+}
+
+#[tokio::test]
+async fn top_up_and_edits_apply_until_the_first_submission() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(3 * SOL).await;
+    let agent = harness.agent("patient").await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    let escrow = pda::escrow(0);
+    let top_up = |amount| instructions::top_up_bounty(&client.pubkey(), &task, &escrow, amount, None);
+    assert_rejected(harness.send(top_up(0), &[&client]).await, OpenfourrError::InvalidBounty);
+    let (client_before, escrow_before) = (harness.lamports(&client.pubkey()).await, harness.lamports(&escrow).await);
+    harness.send(top_up(SOL / 2), &[&client]).await.unwrap();
+    assert_eq!(client_before - harness.lamports(&client.pubkey()).await, SOL / 2);
+    assert_eq!(harness.lamports(&escrow).await - escrow_before, SOL / 2);
+
+    let update = || {
+        instructions::update_task(
+            &client.pubkey(),
+            &task,
+            "Label the bigger dataset".to_string(),
+            "Label 1500 images".to_string(),
+            String::new(),
+            "data".to_string(),
+            72,
+            false,
+        )
+    };
+    harness.send(update(), &[&client]).await.unwrap();
+    let state = harness.read::<Task>(&task).await;
+    assert_eq!(state.title, "Label the bigger dataset");
+    assert_eq!(state.bounty_amount, SOL + SOL / 2);
+
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    assert_rejected(harness.send(update(), &[&client]).await, OpenfourrError::HasSubmissions);
+
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(SOL + SOL / 2));
+}
+
+#[tokio::test]
+async fn cancelled_crowdfund_refunds_client_and_funders_pro_rata() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let funders = [harness.wallet(SOL).await, harness.wallet(SOL).await];
+
+    let crowdfunded = TaskOptions { crowdfunded: true, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, 600_000_000, crowdfunded, None).await;
+    let escrow = pda::escrow(0);
+    let contribute = |funder: &Keypair, amount| instructions::contribute(&funder.pubkey(), &task, &escrow, amount, None);
+    assert_rejected(harness.send(contribute(&client, 1), &[&client]).await, OpenfourrError::ClientCannotContribute);
+    harness.send(contribute(&funders[0], 300_000_000), &[&funders[0]]).await.unwrap();
+    harness.send(contribute(&funders[1], 100_000_000), &[&funders[1]]).await.unwrap();
+    assert_eq!(harness.read::<Task>(&task).await.bounty_amount, SOL);
+
+    let authority = harness.authority();
+    let claim = |payer: &Pubkey, funder: &Keypair| {
+        instructions::claim_refund(payer, &funder.pubkey(), &task, &escrow, &client.pubkey(), None)
+    };
+    assert_rejected(harness.send(claim(&authority, &funders[0]), &[]).await, OpenfourrError::TaskNotFinished);
+
+    // The client takes back their own share; the funders' shares wait in the escrow
+    let client_before = harness.lamports(&client.pubkey()).await;
+    harness.send(instructions::cancel_task(&client.pubkey(), &task, &escrow, false, None), &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 600_000_000);
+    assert_eq!(harness.read::<Task>(&task).await.refund_pool, 400_000_000);
+
+    // A funder can claim their own refund, or anyone can send it to them
+    for (funder, share, by_funder) in [(&funders[0], 300_000_000, true), (&funders[1], 100_000_000, false)] {
+        let contribution = pda::contribution(&task, &funder.pubkey());
+        let (funder_before, contribution_rent) = (harness.lamports(&funder.pubkey()).await, harness.lamports(&contribution).await);
+        if by_funder {
+            harness.send(claim(&funder.pubkey(), funder), &[funder]).await.unwrap();
+        } else {
+            harness.send(claim(&authority, funder), &[]).await.unwrap();
+        }
+        assert_eq!(harness.lamports(&funder.pubkey()).await - funder_before, share + contribution_rent);
+        assert!(!harness.exists(&contribution).await);
+    }
+    // The last claim empties the escrow and returns its rent reserve to the client
+    assert!(!harness.exists(&escrow).await);
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 600_000_000 + harness.rent_reserve().await);
+}
+
+#[tokio::test]
+async fn completed_crowdfund_closes_without_its_funders() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let funder = harness.wallet(SOL).await;
+    let agent = harness.agent("paid").await;
+    let authority = harness.authority();
+    harness.send(instructions::set_retention_period(&authority, 3600), &[]).await.unwrap();
+
+    let crowdfunded = TaskOptions { crowdfunded: true, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, 600_000_000, crowdfunded, None).await;
+    let escrow = pda::escrow(0);
+    harness.send(instructions::contribute(&funder.pubkey(), &task, &escrow, 400_000_000, None), &[&funder]).await.unwrap();
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+    harness.send(instructions::rate_client(&agent.pubkey(), &task, &client.pubkey(), 5), &[&agent]).await.unwrap();
+
+    let completed_at = harness.read::<Task>(&task).await.completed_at.unwrap();
+    harness.warp_to(completed_at + 3600).await;
+    harness.send(instructions::close_submission(&task, &agent.pubkey(), true), &[]).await.unwrap();
+    let close_task = instructions::close_task(&task, &client.pubkey(), false);
+    assert_rejected(harness.send(close_task.clone(), &[]).await, OpenfourrError::HasContributions);
+
+    // The funder never shows up; anyone closes the contribution and its rent still goes to them
+    let contribution = pda::contribution(&task, &funder.pubkey());
+    let (funder_before, contribution_rent) = (harness.lamports(&funder.pubkey()).await, harness.lamports(&contribution).await);
+    let claim = instructions::claim_refund(&authority, &funder.pubkey(), &task, &escrow, &client.pubkey(), None);
+    harness.send(claim, &[]).await.unwrap();
+    assert_eq!(harness.lamports(&funder.pubkey()).await - funder_before, contribution_rent);
+    harness.send(close_task, &[]).await.unwrap();
+    assert!(!harness.exists(&task).await);
+}
+
+#[tokio::test]
+async fn funder_majority_binds_the_winner() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let funders = [harness.wallet(SOL).await, harness.wallet(SOL).await];
+    let agents = [harness.agent("client-pick").await, harness.agent("crowd-pick").await];
+
+    // The client stakes most of the bounty, but only the funders' money counts towards their majority
+    let crowdfunded = TaskOptions { crowdfunded: true, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, 600_000_000, crowdfunded, None).await;
+    let escrow = pda::escrow(0);
+    let contribute = |funder: &Keypair, amount| instructions::contribute(&funder.pubkey(), &task, &escrow, amount, None);
+    harness.send(contribute(&funders[0], 300_000_000), &[&funders[0]]).await.unwrap();
+    harness.send(contribute(&funders[1], 100_000_000), &[&funders[1]]).await.unwrap();
+    for agent in &agents {
+        harness.send(submit(agent, &task), &[agent]).await.unwrap();
+    }
+    let vote = |funder: &Keypair, agent: &Keypair| instructions::vote_winner(&funder.pubkey(), &task, &agent.pubkey());
+    harness.send(vote(&funders[1], &agents[0]), &[&funders[1]]).await.unwrap();
+    assert_rejected(harness.send(contribute(&funders[1], 1), &[&funders[1]]).await, OpenfourrError::ContributionLocked);
+    assert_eq!(harness.read::<Task>(&task).await.vote_leader, None);
+    harness.send(vote(&funders[0], &agents[1]), &[&funders[0]]).await.unwrap();
+
+    let select = |agent: &Keypair| instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, None);
+    assert_rejected(harness.send(select(&agents[0]), &[&client]).await, OpenfourrError::OverruledByFunders);
+    let agent_before = harness.lamports(&agents[1].pubkey()).await;
+    harness.send(select(&agents[1]), &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agents[1].pubkey()).await - agent_before, after_fee(SOL));
+}
+
+#[tokio::test]
+async fn dispute_slashes_the_agent_stake_to_the_treasury() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("bonded").await, harness.agent("unbonded").await];
+    let authority = harness.authority();
+    assert_rejected(harness.send(instructions::set_slash_bps(&authority, 10_001), &[]).await, OpenfourrError::InvalidSlash);
+    harness.send(instructions::set_slash_bps(&authority, 5000), &[]).await.unwrap();
+
+    let stake_vault = pda::stake_vault(&agents[0].pubkey());
+    harness.send(instructions::stake(&agents[0].pubkey(), 200_000_000), &[&agents[0]]).await.unwrap();
+    let reserve = harness.rent_reserve().await;
+    assert_eq!(harness.lamports(&stake_vault).await, 200_000_000 + reserve);
+
+    let bonded = TaskOptions { min_stake: 200_000_000, ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, SOL, bonded, None).await;
+    let escrow = pda::escrow(0);
+    assert_rejected(harness.send(submit(&agents[1], &task), &[&agents[1]]).await, OpenfourrError::InsufficientStake);
+    harness.send(submit(&agents[0], &task), &[&agents[0]]).await.unwrap();
+    let dispute = instructions::raise_dispute(&client.pubkey(), &task, "Plagiarised".to_string(), String::new(), None);
+    harness.send(dispute, &[&client]).await.unwrap();
+
+    let treasury_before = harness.lamports(&pda::treasury()).await;
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let dispute_lamports = harness.lamports(&pda::dispute(&task)).await;
+    let resolve = instructions::resolve_dispute(
+        &authority,
+        &task,
+        &escrow,
+        &client.pubkey(),
+        &client.pubkey(),
+        Some(agents[0].pubkey()),
+        DisputeResolution::RefundClient,
+        None,
+        Some(SlashTarget::Treasury),
+        true,
+        None,
+    );
+    harness.send(resolve, &[]).await.unwrap();
+
+    // Half the stake goes to the treasury, none of it to the refunded client
+    assert_eq!(harness.lamports(&pda::treasury()).await - treasury_before, 100_000_000);
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, SOL + reserve + dispute_lamports);
+    assert_eq!(harness.lamports(&stake_vault).await, 100_000_000 + reserve);
+    assert_eq!(harness.read::<AgentProfile>(&pda::agent(&agents[0].pubkey())).await.staked, 100_000_000);
+}
+
+#[tokio::test]
+async fn unstake_waits_out_the_cooldown_and_returns_the_vault_rent() {
+    let mut harness = Harness::start().await;
+    let agent = harness.agent("leaving").await;
+    let stake_vault = pda::stake_vault(&agent.pubkey());
+    harness.send(instructions::stake(&agent.pubkey(), 200_000_000), &[&agent]).await.unwrap();
+
+    assert_rejected(
+        harness.send(instructions::request_unstake(&agent.pubkey(), 200_000_001), &[&agent]).await,
+        OpenfourrError::InvalidAmount,
+    );
+    harness.send(instructions::request_unstake(&agent.pubkey(), 200_000_000), &[&agent]).await.unwrap();
+    let withdraw = instructions::withdraw_stake(&agent.pubkey());
+    assert_rejected(harness.send(withdraw.clone(), &[&agent]).await, OpenfourrError::UnstakeCooldownActive);
+
+    let available_at = harness.read::<AgentProfile>(&pda::agent(&agent.pubkey())).await.unstake_available_at;
+    harness.warp_to(available_at).await;
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    harness.send(withdraw, &[&agent]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, 200_000_000 + harness.rent_reserve().await);
+    assert!(!harness.exists(&stake_vault).await);
+}
+
+#[tokio::test]
+async fn client_and_winner_rate_each_other_once() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("rated").await, harness.agent("unrated").await];
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    for agent in &agents {
+        harness.send(submit(agent, &task), &[agent]).await.unwrap();
+    }
+    let select = instructions::select_winner(&client.pubkey(), &task, &pda::escrow(0), &agents[0].pubkey(), 4, None);
+    harness.send(select, &[&client]).await.unwrap();
+    let winner = harness.read::<AgentProfile>(&pda::agent(&agents[0].pubkey())).await;
+    assert_eq!((winner.rating_sum, winner.rating_count, winner.total_earned), (4, 1, after_fee(SOL)));
+
+    let rate = |agent: &Keypair, rating| instructions::rate_client(&agent.pubkey(), &task, &client.pubkey(), rating);
+    assert_rejected(harness.send(rate(&agents[1], 5), &[&agents[1]]).await, OpenfourrError::NotWinner);
+    assert_rejected(harness.send(rate(&agents[0], 6), &[&agents[0]]).await, OpenfourrError::InvalidRating);
+    harness.send(rate(&agents[0], 3), &[&agents[0]]).await.unwrap();
+    assert_rejected(harness.send(rate(&agents[0], 5), &[&agents[0]]).await, OpenfourrError::AlreadyRated);
+
+    let profile = harness.read::<ClientProfile>(&pda::client_profile(&client.pubkey())).await;
+    assert_eq!((profile.tasks_posted, profile.tasks_completed, profile.total_paid), (1, 1, SOL));
+    assert_eq!((profile.rating_sum, profile.rating_count), (3, 1));
+}
+
+#[tokio::test]
+async fn select_winners_pays_the_schedule_and_refunds_unfilled_places() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agents = [harness.agent("first").await, harness.agent("second").await];
+
+    let split = TaskOptions { payout_schedule: vec![6000, 3000, 1000], ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, SOL, split, None).await;
+    let escrow = pda::escrow(0);
+    for agent in &agents {
+        harness.send(submit(agent, &task), &[agent]).await.unwrap();
+    }
+
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agents[0].pubkey(), 5, None);
+    assert_rejected(harness.send(select, &[&client]).await, OpenfourrError::MultipleWinnersRequired);
+    let winners = |order: [&Keypair; 2]| {
+        let winners = order.map(|agent| agent.pubkey());
+        instructions::select_winners(&client.pubkey(), &task, &escrow, &winners, vec![5, 4], None)
+    };
+    assert_rejected(harness.send(winners([&agents[0], &agents[0]]), &[&client]).await, OpenfourrError::DuplicateWinner);
+
+    let before = [harness.lamports(&agents[0].pubkey()).await, harness.lamports(&agents[1].pubkey()).await];
+    let client_before = harness.lamports(&client.pubkey()).await;
+    harness.send(winners([&agents[0], &agents[1]]), &[&client]).await.unwrap();
+
+    assert_eq!(harness.lamports(&agents[0].pubkey()).await - before[0], after_fee(600_000_000));
+    assert_eq!(harness.lamports(&agents[1].pubkey()).await - before[1], after_fee(300_000_000));
+    // The unfilled third place goes back to the client with the escrow's rent
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 100_000_000 + harness.rent_reserve().await);
+    assert!(!harness.exists(&escrow).await);
+    assert!(harness.read::<Task>(&task).await.status == TaskStatus::Completed);
+}
+
+#[tokio::test]
+async fn only_participants_can_dispute_and_a_won_dispute_returns_the_bond() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("wronged").await;
+    let outsider = harness.agent("outsider").await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let dispute = |raiser: &Keypair, submission| {
+        instructions::raise_dispute(&raiser.pubkey(), &task, "Ignored".to_string(), String::new(), submission)
+    };
+    let agent_submission = Some(pda::submission(&task, &agent.pubkey()));
+    assert_rejected(harness.send(dispute(&outsider, None), &[&outsider]).await, OpenfourrError::NotParticipant);
+    // Someone else's submission doesn't make the outsider a participant
+    assert_rejected(harness.send(dispute(&outsider, agent_submission), &[&outsider]).await, OpenfourrError::NotParticipant);
+
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    harness.send(dispute(&agent, agent_submission), &[&agent]).await.unwrap();
+    let dispute_lamports = harness.lamports(&pda::dispute(&task)).await;
+    assert_eq!(agent_before - harness.lamports(&agent.pubkey()).await, dispute_lamports);
+
+    let resolve = instructions::resolve_dispute(
+        &harness.authority(),
+        &task,
+        &escrow,
+        &client.pubkey(),
+        &agent.pubkey(),
+        Some(agent.pubkey()),
+        DisputeResolution::AwardSubmission,
+        Some(5),
+        None,
+        true,
+        None,
+    );
+    harness.send(resolve, &[]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(SOL));
+    assert!(!harness.exists(&pda::dispute(&task)).await);
+}
+
+#[tokio::test]
+async fn client_tasks_use_per_client_nonces_and_escrows() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(3 * SOL).await;
+    let agent = harness.agent("per-client").await;
+
+    let create = |nonce| {
+        instructions::create_client_task(
+            &client.pubkey(),
+            nonce,
+            "Label the dataset".to_string(),
+            "Label 1000 images".to_string(),
+            String::new(),
+            "data".to_string(),
+            SOL / 2,
+            48,
+            options(TaskMode::OpenCompetition),
+            None,
+        )
+    };
+    harness.send(create(0), &[&client]).await.unwrap();
+    // A stale nonce derives the address of a task that already exists
+    assert!(harness.send(create(0), &[&client]).await.is_err());
+    harness.send(create(1), &[&client]).await.unwrap();
+
+    let task = pda::client_task(&client.pubkey(), 1);
+    let escrow = pda::client_escrow(&task);
+    let state = harness.read::<Task>(&task).await;
+    assert!(state.per_client && state.id == 1);
+    assert_eq!(harness.read::<ClientProfile>(&pda::client_profile(&client.pubkey())).await.task_nonce, 2);
+    assert_eq!(harness.lamports(&escrow).await, SOL / 2 + harness.rent_reserve().await);
+
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+    let agent_before = harness.lamports(&agent.pubkey()).await;
+    let select = instructions::select_winner(&client.pubkey(), &task, &escrow, &agent.pubkey(), 5, None);
+    harness.send(select, &[&client]).await.unwrap();
+    assert_eq!(harness.lamports(&agent.pubkey()).await - agent_before, after_fee(SOL / 2));
+    assert!(!harness.exists(&escrow).await);
+    // The other client task keeps its own escrow
+    assert!(harness.exists(&pda::client_escrow(&pda::client_task(&client.pubkey(), 0))).await);
+}
+
+#[tokio::test]
+async fn text_fields_are_checked_before_any_funds_move() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let owner = harness.wallet(SOL).await;
+
+    let register = instructions::register_agent(&owner.pubkey(), "two\nlines".to_string(), String::new(), vec![], 0);
+    assert_rejected(harness.send(register, &[&owner]).await, OpenfourrError::NameInvalidCharacters);
+    assert!(!harness.exists(&pda::agent(&owner.pubkey())).await);
+
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let create = |title: &str, category: &str| {
+        instructions::create_task(
+            &client.pubkey(),
+            0,
+            title.to_string(),
+            "Label 1000 images".to_string(),
+            String::new(),
+            category.to_string(),
+            SOL,
+            48,
+            options(TaskMode::OpenCompetition),
+            None,
+        )
+    };
+    assert_rejected(harness.send(create(" ", "data"), &[&client]).await, OpenfourrError::TitleEmpty);
+    assert_rejected(harness.send(create("Label", "da\tta"), &[&client]).await, OpenfourrError::CategoryInvalidCharacters);
+    assert_eq!(harness.lamports(&client.pubkey()).await, client_before);
+    assert!(!harness.exists(&pda::escrow(0)).await);
+}
+
+#[tokio::test]
+async fn select_winners_reports_the_unfilled_refund() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("only").await;
+
+    let split = TaskOptions { payout_schedule: vec![7000, 3000], ..options(TaskMode::OpenCompetition) };
+    let task = harness.create_task(&client, SOL, split, None).await;
+    let escrow = pda::escrow(0);
+    harness.send(submit(&agent, &task), &[&agent]).await.unwrap();
+
+    let select = instructions::select_winners(&client.pubkey(), &task, &escrow, &[agent.pubkey()], vec![5], None);
+    let client_before = harness.lamports(&client.pubkey()).await;
+    let refunds = harness.send_for_events::<UnfilledPlacesRefunded>(select, &[&client]).await;
+    assert_eq!(refunds.len(), 1);
+    assert_eq!((refunds[0].task, refunds[0].client, refunds[0].refund), (task, client.pubkey(), 300_000_000));
+    assert_eq!(harness.lamports(&client.pubkey()).await - client_before, 300_000_000 + harness.rent_reserve().await);
+}
+
+#[tokio::test]
+async fn rejection_event_names_the_submission() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(2 * SOL).await;
+    let agent = harness.agent("rejected").await;
+
+    let task = harness.create_task(&client, SOL, options(TaskMode::ExclusiveClaim), None).await;
+    harness.send(instructions::claim_task(&agent.pubkey(), &task), &[&agent]).await.unwrap();
+    let work = instructions::submit_work(&agent.pubkey(), &task, "https://example.com/v1.csv".to_string(), String::new());
+    harness.send(work, &[&agent]).await.unwrap();
+    let reject = instructions::reject_work(&client.pubkey(), &task, &agent.pubkey(), "Missing rows".to_string());
+    let rejections = harness.send_for_events::<WorkRejected>(reject, &[&client]).await;
+    assert_eq!(rejections.len(), 1);
+    assert_eq!(rejections[0].submission, pda::submission(&task, &agent.pubkey()));
+}
+
+#[tokio::test]
+async fn client_decodes_tasks_and_derives_their_escrows() {
+    let mut harness = Harness::start().await;
+    let client = harness.wallet(3 * SOL).await;
+
+    let global = harness.create_task(&client, SOL, options(TaskMode::OpenCompetition), None).await;
+    let create = instructions::create_client_task(
+        &client.pubkey(),
+        0,
+        "Label the dataset".to_string(),
+        "Label 1000 images".to_string(),
+        String::new(),
+        "data".to_string(),
+        SOL / 2,
+        48,
+        options(TaskMode::OpenCompetition),
+        None,
+    );
+    harness.send(create, &[&client]).await.unwrap();
+    let per_client = pda::client_task(&client.pubkey(), 0);
+
+    let reserve = harness.rent_reserve().await;
+    for (task, bounty) in [(global, SOL), (per_client, SOL / 2)] {
+        let data = harness.context.banks_client.get_account(task).await.unwrap().unwrap().data;
+        // Decoders check the discriminator, so a task never passes for another account type
+        assert!(state::decode_agent_profile(&data).is_err());
+        let decoded = state::decode_task(&data).unwrap();
+        let escrow = pda::task_escrow(&task, &decoded);
+        assert_eq!(harness.lamports(&escrow).await, bounty + reserve);
+
+        let client_before = harness.lamports(&client.pubkey()).await;
+        harness.send(instructions::cancel_task(&client.pubkey(), &task, &escrow, false, None), &[&client]).await.unwrap();
+        assert_eq!(harness.lamports(&client.pubkey()).await - client_before, bounty + reserve);
+    }
+}